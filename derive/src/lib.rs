@@ -0,0 +1,211 @@
+//! `#[derive(Diagnostic)]` for the `reporter` crate, see `reporter::Diagnostic`
+
+use ::proc_macro::TokenStream;
+use ::proc_macro2::TokenStream as TokenStream2;
+use ::quote::{format_ident, quote};
+use ::syn::{
+    Attribute, Data, DeriveInput, Fields, LitStr, Meta, parse_macro_input, spanned::Spanned,
+};
+
+/// Implements `reporter::Diagnostic`, building a report out of the annotated fields
+///
+/// - `#[label("expected {expected}")]` on a field holding a range (anything implementing `IntoRange`)
+///   adds a label with the formatted message, fields being referenced by their names (`_0`, `_1`, ... for tuples).
+///   A bare `#[label]` uses the `Display` output of the whole value as its message.
+/// - `#[child("...")]` on a labeled field adds a child label to it,
+///   on a variant (or struct) it adds a child label to its first label.
+/// - `#[severity(error | warning | note | help)]` on the type or a variant, the latter taking precedence.
+#[proc_macro_derive(Diagnostic, attributes(label, child, severity))]
+pub fn derive_diagnostic(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(::syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> ::syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let severity = severity(&input.attrs)?;
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, build) =
+                variant(quote!(Self), &data.fields, &input.attrs, severity.as_ref())?;
+            quote! {
+                let #pattern = self;
+                #build
+            }
+        }
+        // A reference to an uninhabited type still needs to be matched on
+        Data::Enum(data) if data.variants.is_empty() => quote!(match *self {}),
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let ident = &variant.ident;
+                    let severity = self::severity(&variant.attrs)?.or_else(|| severity.clone());
+                    let (pattern, build) = self::variant(
+                        quote!(Self::#ident),
+                        &variant.fields,
+                        &variant.attrs,
+                        severity.as_ref(),
+                    )?;
+                    Ok(quote!(#pattern => { #build }))
+                })
+                .collect::<::syn::Result<Vec<_>>>()?;
+            quote!(match self { #(#arms)* })
+        }
+        Data::Union(data) => {
+            return Err(::syn::Error::new(
+                data.union_token.span(),
+                "Diagnostic can not be derived for unions",
+            ));
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::reporter::Diagnostic for #name #ty_generics #where_clause {
+            // Every field is bound, no matter whether a message references it.
+            // The fields are bound by their own names, so the builder and input are prefixed
+            // to not be shadowed by fields called `report` or `input`
+            #[allow(unused_variables)]
+            fn report<'src, I: ::core::convert::Into<::reporter::SourceText<'src>>>(
+                &self,
+                __reporter_input: I,
+            ) -> ::reporter::ReportBuilder<'src> {
+                let mut __reporter_report = ::reporter::ReportBuilder::new(__reporter_input);
+                #body
+                __reporter_report
+            }
+        }
+    })
+}
+
+/// The pattern binding all fields of a struct or variant (given by its path),
+/// along with the statements adding its severity and labels to `__reporter_report`
+fn variant(
+    path: TokenStream2,
+    fields: &Fields,
+    attrs: &[Attribute],
+    severity: Option<&TokenStream2>,
+) -> ::syn::Result<(TokenStream2, TokenStream2)> {
+    let bindings = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            field
+                .ident
+                .clone()
+                .unwrap_or_else(|| format_ident!("_{}", index))
+        })
+        .collect::<Vec<_>>();
+    let pattern = match fields {
+        Fields::Named(_) => quote!(#path { #(#bindings),* }),
+        Fields::Unnamed(_) => quote!(#path ( #(#bindings),* )),
+        Fields::Unit => path.clone(),
+    };
+
+    let mut labels = Vec::new();
+    fields
+        .iter()
+        .zip(&bindings)
+        .try_for_each(|(field, binding)| -> ::syn::Result<()> {
+            let children = children(&field.attrs)?;
+            let Some(message) = label(&field.attrs)? else {
+                return match children.first() {
+                    Some(child) => Err(::syn::Error::new(
+                        child.span(),
+                        "#[child] requires a #[label] on the same field",
+                    )),
+                    None => Ok(()),
+                };
+            };
+            let message = match message {
+                Some(message) => quote!(::std::format!(#message)),
+                None => quote!(::std::string::ToString::to_string(self)),
+            };
+            labels.push(quote! {
+                ::reporter::Label::new(
+                    ::reporter::IntoRange::into_range(::core::clone::Clone::clone(#binding)),
+                    #message,
+                )
+                #(.with_child_label(::reporter::ChildLabel::new(::std::format!(#children))))*
+            });
+            Ok(())
+        })?;
+
+    // Children of the variant itself belong to its first label
+    let children = children(attrs)?;
+    match labels.first_mut() {
+        Some(first) => first.extend(quote! {
+            #(.with_child_label(::reporter::ChildLabel::new(::std::format!(#children))))*
+        }),
+        None => {
+            return Err(::syn::Error::new(
+                path.span(),
+                "at least one field needs a #[label], as a report can not be empty",
+            ));
+        }
+    }
+
+    let severity = severity
+        .map(|severity| quote!(__reporter_report = __reporter_report.with_severity(#severity);));
+    Ok((
+        pattern,
+        quote! {
+            #severity
+            #(__reporter_report.push(#labels);)*
+        },
+    ))
+}
+
+/// `#[label]` gives None, `#[label("...")]` the message
+fn label(attrs: &[Attribute]) -> ::syn::Result<Option<Option<LitStr>>> {
+    let mut labels = attrs.iter().filter(|attr| attr.path().is_ident("label"));
+    let Some(attr) = labels.next() else {
+        return Ok(None);
+    };
+    if let Some(duplicate) = labels.next() {
+        return Err(::syn::Error::new(
+            duplicate.span(),
+            "a field can only have a single #[label]",
+        ));
+    }
+    match &attr.meta {
+        Meta::Path(_) => Ok(Some(None)),
+        _ => attr
+            .parse_args::<LitStr>()
+            .map(|message| Some(Some(message))),
+    }
+}
+
+fn children(attrs: &[Attribute]) -> ::syn::Result<Vec<LitStr>> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("child"))
+        .map(|attr| attr.parse_args::<LitStr>())
+        .collect()
+}
+
+/// The path of the `Severity` given by `#[severity(...)]`, if any
+fn severity(attrs: &[Attribute]) -> ::syn::Result<Option<TokenStream2>> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("severity")) else {
+        return Ok(None);
+    };
+    let ident = attr.parse_args::<::syn::Ident>()?;
+    let variant = match ident.to_string().as_str() {
+        "error" => quote!(Error),
+        "warning" => quote!(Warning),
+        "note" => quote!(Note),
+        "help" => quote!(Help),
+        _ => {
+            return Err(::syn::Error::new(
+                ident.span(),
+                "expected one of `error`, `warning`, `note` or `help`",
+            ));
+        }
+    };
+    Ok(Some(quote!(::reporter::Severity::#variant)))
+}
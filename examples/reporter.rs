@@ -1,6 +1,11 @@
 use ::std::usize;
 
-use ::reporter::{AnsiStyle, ChildLabel, Error, Label, ReportBuilder, Trim, TrimPadding};
+#[cfg(feature = "serde")]
+use ::reporter::SarifLog;
+use ::reporter::{
+    AnsiStyle, ChildLabel, ColorDepth, Error, ErrorChain, GlyphSet, IntoRange, Label, Palette,
+    RangeInclusive, ReportBuilder, Severity, Sources, Spanned, Trim, TrimPadding,
+};
 
 fn single_line() {
     let mut report = ReportBuilder::new("Longer Test - Another test input");
@@ -9,9 +14,7 @@ fn single_line() {
         .with_child_label(ChildLabel::new("Child label Y"));
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 
 fn multi_line_label_and_childlabel() {
@@ -26,9 +29,7 @@ fn multi_line_label_and_childlabel() {
         .with_child_label(ChildLabel::new("Child label 3"));
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 
 fn three_labels() {
@@ -48,9 +49,7 @@ fn three_labels() {
         .with_child_label(ChildLabel::new("Third Child label 3"));
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 
 fn with_color() {
@@ -61,9 +60,7 @@ fn with_color() {
         .with_child_label(ChildLabel::new("Child label B"));
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 
 fn multiline_overlapping_labels() {
@@ -82,9 +79,7 @@ fn multiline_overlapping_labels() {
         .with_child_label(ChildLabel::new("Child label 3\nwith two lines"));
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 
 fn write_iter() {
@@ -235,9 +230,7 @@ fn single_label_with_color_and_caret_color() {
         .with_child_label(ChildLabel::new("Child label B"));
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 #[cfg(feature = "caret_color")]
 fn overlapping_labels_with_color_and_caret_color() {
@@ -267,9 +260,7 @@ fn overlapping_labels_with_color_and_caret_color() {
         );
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 
 #[cfg(not(feature = "merge_overlap"))]
@@ -286,9 +277,7 @@ fn directly_overlaping_labels() {
         .with_child_label(ChildLabel::new("Child label Y"));
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 
 #[cfg(feature = "merge_overlap")]
@@ -310,9 +299,7 @@ fn directly_overlaping_labels_merged() {
         .with_child_label(ChildLabel::new("Child label 2").with_color(AnsiStyle::WHITE));
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 #[cfg(all(feature = "merge_overlap", feature = "caret_color"))]
 fn directly_overlaping_labels_merged_with_caret_color() {
@@ -354,9 +341,7 @@ fn directly_overlaping_labels_merged_with_caret_color() {
         );
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 
 fn multiline_overlapping_labels_colored_input() {
@@ -367,9 +352,7 @@ fn multiline_overlapping_labels_colored_input() {
         .with_child_label(ChildLabel::new("Child label B"));
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 
 #[cfg(feature = "truncate_out_of_bounds")]
@@ -380,9 +363,7 @@ fn out_of_bounds_truncate_silent() {
     let label = Label::new(0..20, "Out of bounds label");
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 #[cfg(feature = "truncate_out_of_bounds")]
 fn out_of_bounds_truncate_indicated() {
@@ -393,9 +374,7 @@ fn out_of_bounds_truncate_indicated() {
     let label = Label::new(0..20, "Out of bounds label");
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 
 fn very_long_label() {
@@ -406,9 +385,7 @@ fn very_long_label() {
     );
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 
 fn very_long_input_trimmed_long() {
@@ -421,9 +398,7 @@ fn very_long_input_trimmed_long() {
     );
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 fn very_long_input_trimmed_tight() {
     let mut report =
@@ -435,9 +410,7 @@ fn very_long_input_trimmed_tight() {
     );
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 fn very_long_input_trimmed_front() {
     let mut report =
@@ -449,9 +422,7 @@ fn very_long_input_trimmed_front() {
     );
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 fn very_long_input_trimmed_back() {
     let mut report =
@@ -463,9 +434,7 @@ fn very_long_input_trimmed_back() {
     );
     report.push(label);
     let report = report.finish().unwrap();
-    let mut output = Vec::new();
-    report.write(&mut output).unwrap();
-    print!("{}", String::from_utf8_lossy(&output));
+    print!("{report}");
 }
 fn very_long_input_trimmed_chars() {
     let mut report =
@@ -477,11 +446,316 @@ fn very_long_input_trimmed_chars() {
     );
     report.push(label);
     let report = report.finish().unwrap();
+    print!("{report}");
+}
+
+fn multi_line_source() {
+    let source = "[package]\nname = \"reporter\"\nversion = 1.3.0\nedition = \"2024\"";
+    let mut report = ReportBuilder::new(source).trim_input(Trim::None);
+    let label = Label::new(38..=42, "Expected a string")
+        .with_child_label(ChildLabel::new("Versions must be quoted"));
+    report.push(label);
+    let label = Label::new(10..=13, "Package name defined here");
+    report.push(label);
+    let report = report.finish().unwrap();
+    print!("{report}");
+}
+
+fn multi_line_source_with_line_numbers() {
+    let source = "[package]\nname = \"reporter\"\nversion = 1.3.0\nedition = \"2024\"";
+    let mut report = ReportBuilder::new(source)
+        .trim_input(Trim::None)
+        .with_line_numbers();
+    let label = Label::new(38..=42, "Expected a string")
+        .with_child_label(ChildLabel::new("Versions must be quoted"));
+    report.push(label);
+    let label = Label::new(10..=13, "Package name defined here");
+    report.push(label);
+    let report = report.finish().unwrap();
+    print!("{report}");
+}
+
+fn multi_line_span() {
+    let source = "[package]\nname = \"reporter\"\nversion = 1.3.0\nedition = \"2024\"";
+    let mut report = ReportBuilder::new(source)
+        .trim_input(Trim::None)
+        .with_line_numbers();
+    let label = Label::new(0..=41, "Package metadata")
+        .with_child_label(ChildLabel::new("Missing a license"));
+    report.push(label);
+    let label = Label::new(53..=58, "Edition defined here");
+    report.push(label);
+    let report = report.finish().unwrap();
+    print!("{report}");
+}
+
+fn diagnostic_header() {
+    let mut report = ReportBuilder::new("let x = 5 +;")
+        .with_severity(Severity::Error)
+        .with_code("E0042")
+        .with_title("unexpected token");
+    let label = Label::new(10..=11, "Expected an expression");
+    report.push(label);
+    let report = report.finish().unwrap();
+    print!("{report}");
+
+    let mut report = ReportBuilder::new("let x = 5 +;")
+        .with_severity(Severity::custom("lint", AnsiStyle::BRIGHT_MAGENTA))
+        .with_title("unused variable");
+    let label = Label::new(4..=5, "Never read");
+    report.push(label);
+    let report = report.finish().unwrap();
+    print!("{report}");
+}
+
+fn source_name_location() {
+    let source = "[package]\nname = \"reporter\"\nversion = 1.3.0\nedition = \"2024\"";
+    let mut report = ReportBuilder::new(source)
+        .trim_input(Trim::None)
+        .with_line_numbers()
+        .with_source_name("Cargo.toml")
+        .with_title("invalid manifest");
+    let label = Label::new(37..=42, "Expected a string");
+    report.push(label);
+    let report = report.finish().unwrap();
+    print!("{report}");
+}
+
+fn multiple_files() {
+    let mut sources = Sources::new();
+    let a = sources.add(
+        "a.toml",
+        "[dependencies]\nshared = { path = \"../shared\" }",
+    );
+    let b = sources.add("b.toml", "[dependencies]\nshared = \"1.0\"");
+    let mut report = ReportBuilder::from_sources(sources)
+        .trim_input(Trim::None)
+        .with_line_numbers()
+        .with_severity(Severity::Warning)
+        .with_title("conflicting dependency sources");
+    report.push(Label::new(15..=20, "defined here").in_file(a));
+    report.push(Label::new(24..=28, "used here").in_file(b));
+    let report = report.finish().unwrap();
+    print!("{report}");
+}
+
+fn footer_notes() {
+    let source = "[package]\nname = \"reporter\"\nversion = 1.3.0\nedition = \"2024\"";
+    let mut report = ReportBuilder::new(source)
+        .trim_input(Trim::None)
+        .with_line_numbers()
+        .with_title("invalid manifest")
+        .with_note("Versions follow the semantic versioning scheme")
+        .with_help(
+            "Quote the version, e.g. `version = \"1.3.0\"`, as TOML does not know about version literals and will fail to parse it otherwise",
+        )
+        .max_footer_length(60);
+    let label = Label::new(37..=42, "Expected a string");
+    report.push(label);
+    let label = Label::new(53..=58, "Edition defined here");
+    report.push(label);
+    let report = report.finish().unwrap();
+    print!("{report}");
+}
+
+fn fix_it_suggestion() {
+    let source = "[package]\nname = \"reporter\"\nversion = 1.3.0\neditn = \"2024\"";
+    let mut report = ReportBuilder::new(source)
+        .trim_input(Trim::None)
+        .with_line_numbers()
+        .with_title("invalid manifest");
+    let label = Label::new(38..=42, "Expected a string")
+        .with_insertion(38, "\"")
+        .with_insertion(43, "\"");
+    report.push(label);
+    let label = Label::new(44..=48, "Unknown key").with_suggestion(44..=48, "edition");
+    report.push(label);
+    let report = report.finish().unwrap();
+    // The suggestions are available as plain data as well, e.g. to be applied by an editor
+    report.suggestions().for_each(|suggestion| {
+        println!(
+            "replace {:?} with {:?}",
+            suggestion.range(),
+            suggestion.replacement()
+        );
+    });
+    print!("{report}");
+}
+
+fn tab_expansion() {
+    let source = "main: main.c\n\tcc -o main main.c\tlibfoo.a";
+    let mut report = ReportBuilder::new(source)
+        .trim_input(Trim::None)
+        .with_line_numbers()
+        .tab_width(8);
+    let label = Label::new(32..=39, "No rule to make this target");
+    report.push(label);
+    let report = report.finish().unwrap();
+    print!("{report}");
+}
+
+fn ascii_glyphs() {
+    let source = "[package]\nname = \"reporter\"\nversion = 1.3.0\nedition = \"2024\"";
+    let mut report = ReportBuilder::new(source)
+        .trim_input(Trim::None)
+        .with_line_numbers()
+        .with_glyphs(GlyphSet::ASCII);
+    let label = Label::new(0..=41, "Package metadata")
+        .with_child_label(ChildLabel::new("Missing a license"));
+    report.push(label);
+    let label = Label::new(53..=58, "Edition defined here");
+    report.push(label);
+    let report = report.finish().unwrap();
+    print!("{report}");
+}
+
+fn detected_color_depth() {
+    // Colors are downgraded (or stripped) to whatever stdout supports
+    let mut report = ReportBuilder::new("let answer = 42;")
+        .trim_input(Trim::None)
+        .with_palette(Palette::default())
+        .with_color_depth(ColorDepth::detect(&std::io::stdout()));
+    report.push(Label::new(4..=9, "Automatically colored"));
+    report.push(
+        Label::new(13..=14, "Explicitly colored").with_color(AnsiStyle::new_rgb(255, 128, 0)),
+    );
+    let report = report.finish().unwrap();
+    print!("{report}");
+}
+
+fn html_output() {
+    let source = "let tag = \"<b>bold</b>\";";
+    let mut report = ReportBuilder::new(source)
+        .trim_input(Trim::None)
+        .with_severity(Severity::Warning)
+        .with_title("HTML in a string literal");
+    report.push(Label::new(10..=23, "Escaped in the <pre> block"));
+    let report = report.finish().unwrap();
     let mut output = Vec::new();
-    report.write(&mut output).unwrap();
+    report.write_html(&mut output).unwrap();
     print!("{}", String::from_utf8_lossy(&output));
 }
 
+#[derive(Debug)]
+struct InvalidPort {
+    at: std::ops::Range<usize>,
+    source: std::num::ParseIntError,
+}
+impl std::fmt::Display for InvalidPort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid port number")
+    }
+}
+impl std::error::Error for InvalidPort {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+impl Spanned for InvalidPort {
+    fn span(&self) -> RangeInclusive {
+        self.at.clone().into_range()
+    }
+}
+
+fn error_chain() {
+    let input = "port = 80x";
+    let error = InvalidPort {
+        at: 7..10,
+        source: "80x".parse::<u16>().unwrap_err(),
+    };
+    let report = ReportBuilder::from_error(input, &error, ErrorChain::ChildLabels)
+        .finish()
+        .unwrap();
+    print!("{report}");
+    let report = ReportBuilder::from_error(input, &error, ErrorChain::Notes)
+        .finish()
+        .unwrap();
+    print!("{report}");
+}
+
+#[cfg(feature = "derive")]
+#[derive(Debug, reporter::Diagnostic)]
+enum ConfigError {
+    #[severity(warning)]
+    UnknownKey {
+        #[label("unknown key `{key}`")]
+        #[child("expected one of `host` or `port`")]
+        span: std::ops::Range<usize>,
+        key: String,
+    },
+    Duplicate(
+        #[label] std::ops::Range<usize>,
+        #[label("first defined here")] usize,
+    ),
+}
+#[cfg(feature = "derive")]
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownKey { key, .. } => write!(f, "unknown key {key}"),
+            Self::Duplicate(..) => write!(f, "duplicate key"),
+        }
+    }
+}
+
+#[cfg(feature = "derive")]
+fn derived_diagnostic() {
+    use ::reporter::Diagnostic;
+
+    let input = "host = 1\nprot = 2\nhost = 3";
+    let unknown = ConfigError::UnknownKey {
+        span: 9..13,
+        key: "prot".to_string(),
+    };
+    print!("{}", unknown.report(input).finish().unwrap());
+    let duplicate = ConfigError::Duplicate(18..22, 0);
+    print!("{}", duplicate.report(input).finish().unwrap());
+}
+
+fn render_twice() {
+    let mut report = ReportBuilder::new("let x = 1;").with_severity(Severity::Warning);
+    report.push(Label::new(4..=4, "never read"));
+    let report = report.finish().unwrap();
+    // Once for the log, once for the terminal
+    let log = format!("{report}");
+    let mut output = Vec::new();
+    report.write(&mut output).unwrap();
+    assert_eq!(log.as_bytes(), output);
+    print!("{report}");
+}
+
+#[cfg(feature = "serde")]
+fn json_output() {
+    let mut report = ReportBuilder::new("let x = 1;")
+        .with_source_name("main.rs")
+        .with_severity(Severity::Warning)
+        .with_title("unused variable");
+    report.push(
+        Label::new(4..=4, "never read")
+            .with_child_label(ChildLabel::new("prefix it with an underscore")),
+    );
+    let report = report.finish().unwrap();
+    let mut output = Vec::new();
+    report.write_json(&mut output).unwrap();
+    println!("{}", String::from_utf8_lossy(&output));
+}
+
+#[cfg(feature = "serde")]
+fn sarif_output() {
+    let mut first = ReportBuilder::new("let x = 1;")
+        .with_source_name("src/main.rs")
+        .with_severity(Severity::Warning)
+        .with_code("W001");
+    first.push(Label::new(4..=4, "never read"));
+    let mut second = ReportBuilder::new("let y = x;").with_source_name("src/lib.rs");
+    second.push(Label::new(8..=8, "not in scope"));
+    let mut log = SarifLog::new("linter");
+    log.push_iter(&[first.finish().unwrap(), second.finish().unwrap()]);
+    let mut output = Vec::new();
+    log.write(&mut output).unwrap();
+    println!("{}", String::from_utf8_lossy(&output));
+}
+
 fn main() {
     very_long_input_trimmed_chars();
     println!("----------------------------------------");
@@ -550,4 +824,44 @@ fn main() {
     write_iter_callback();
     println!("----------------------------------------");
     write_iter_callback_none();
+    println!("----------------------------------------");
+    multi_line_source();
+    println!("----------------------------------------");
+    multi_line_source_with_line_numbers();
+    println!("----------------------------------------");
+    multi_line_span();
+    println!("----------------------------------------");
+    diagnostic_header();
+    println!("----------------------------------------");
+    source_name_location();
+    println!("----------------------------------------");
+    multiple_files();
+    println!("----------------------------------------");
+    footer_notes();
+    println!("----------------------------------------");
+    fix_it_suggestion();
+    println!("----------------------------------------");
+    tab_expansion();
+    println!("----------------------------------------");
+    ascii_glyphs();
+    println!("----------------------------------------");
+    detected_color_depth();
+    println!("----------------------------------------");
+    html_output();
+    println!("----------------------------------------");
+    render_twice();
+    println!("----------------------------------------");
+    error_chain();
+    #[cfg(feature = "derive")]
+    {
+        println!("----------------------------------------");
+        derived_diagnostic();
+    }
+    #[cfg(feature = "serde")]
+    {
+        println!("----------------------------------------");
+        json_output();
+        println!("----------------------------------------");
+        sarif_output();
+    }
 }
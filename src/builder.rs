@@ -1,11 +1,17 @@
-use ::std::{fmt::Display, usize};
+use ::std::{collections::BTreeMap, fmt::Display, usize};
 #[cfg(feature = "caret_color")]
 use ::token::RgbColor;
-use ::token::{AnsiStyle, LineTokenStream, saturating::SaturatingArithmetic};
+use ::token::{
+    AnsiStyle, ColorDepth, GlyphSet, LineTokenStream, Palette, saturating::SaturatingArithmetic,
+};
 
-use crate::{Report, TokenizedChildLabel, TokenizedLabelFull};
-
-pub const CHILD_LABEL_PADDING: usize = 4;
+use crate::{
+    FileId, Footer, FooterSummary, Header, LabelSummary, Location, Position, RenderConfig, Report,
+    ReportSummary, Section, SectionLine, Severity, SourceFile, SourceText, Sources,
+    TokenizedChildLabel, TokenizedLabelFull,
+    source::{ExpandedLine, SourceLines},
+    summary::strip_styles,
+};
 
 pub trait IntoRange {
     fn into_range(self) -> RangeInclusive;
@@ -272,17 +278,34 @@ impl TrimPadding {
     }
 }
 #[derive(Debug, Clone)]
-pub struct ReportBuilder {
+pub struct ReportBuilder<'src> {
     /// Will only display the relevant part of the input if true
     trim_input: Trim,
-    /// The full input string thats referenced by the labels
-    input: String,
+    /// The full input strings that are referenced by the labels
+    ///
+    /// Labels point into the first one, unless they are given another file
+    sources: Sources<'src>,
     /// The labels to annotate the input with
     labels: Vec<Label>,
+    /// Printed in the header, defaults to `Severity::Error` if only a code or title is set
+    severity: Option<Severity>,
+    /// Diagnostic code printed next to the severity, e.g. `E0042`
+    code: Option<String>,
+    /// Printed in the header after the severity and code
+    title: Option<String>,
+    /// Free-standing messages printed after all caret blocks, e.g. notes and help messages
+    footers: Vec<(Severity, String)>,
+    /// The length at which footer messages are wrapped
+    max_footer_length: usize,
     display_range: bool,
-    max_label_length: usize,
-    /// If not set, it will be set to "max_label_length - CHILD_LABEL_PADDING" to offset the padding on the child labels
-    max_child_label_length: Option<usize>,
+    /// Will prefix each row with a gutter displaying the line numbers if true
+    line_numbers: bool,
+    /// The number of columns between two tab stops, tabs in the input are expanded to spaces
+    tab_width: usize,
+    /// Layout settings, including the lengths at which label messages are wrapped
+    render_config: RenderConfig,
+    /// Colors the labels that were not given a color, None to leave them uncolored
+    palette: Option<Palette>,
     colored_input: bool,
     #[cfg(feature = "caret_color")]
     caret_color: bool,
@@ -290,16 +313,33 @@ pub struct ReportBuilder {
     truncate_out_of_bounds: TruncateMode,
 }
 
-impl ReportBuilder {
-    pub fn new<I: Into<String>>(input: I) -> Self {
+impl<'src> ReportBuilder<'src> {
+    /// Borrows the input if given as `&str`, see `SourceText` for the alternatives
+    pub fn new<I: Into<SourceText<'src>>>(input: I) -> Self {
+        let mut sources = Sources::new();
+        sources.push(SourceFile::new(input));
+        Self::from_sources(sources)
+    }
+
+    /// Creates a builder whose labels can point into any of the given sources
+    ///
+    /// Labels point into the first source, unless they are given another file via `Label::in_file`
+    pub fn from_sources(sources: Sources<'src>) -> Self {
         Self {
             trim_input: Trim::default(),
             display_range: false,
-            input: input.into(),
+            line_numbers: false,
+            sources,
             labels: Vec::new(),
-            // Default max label length is 30 characters
-            max_label_length: 30,
-            max_child_label_length: None,
+            severity: None,
+            code: None,
+            title: None,
+            footers: Vec::new(),
+            // Footers span (almost) the whole width, thus they may be longer than labels
+            max_footer_length: 80,
+            tab_width: 4,
+            render_config: RenderConfig::default(),
+            palette: None,
             colored_input: false,
             #[cfg(feature = "caret_color")]
             caret_color: false,
@@ -325,6 +365,58 @@ impl ReportBuilder {
         self
     }
 
+    /// Renders the report without any escape sequences, e.g. for log files
+    ///
+    /// Unlike `colored_input(false)`, this also drops the styles of the label messages, carets and footers.
+    pub fn plain_text(mut self, plain: bool) -> Self {
+        self.render_config = self.render_config.plain(plain);
+        self
+    }
+
+    pub fn with_severity<S: Into<Severity>>(mut self, severity: S) -> Self {
+        self.severity = Some(severity.into());
+        self
+    }
+
+    pub fn with_code<I: Display>(mut self, code: I) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    pub fn with_title<I: Display>(mut self, title: I) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// Adds a `note: ...` line, printed after all caret blocks
+    pub fn with_note<I: Display>(self, message: I) -> Self {
+        self.with_footer(Severity::Note, message)
+    }
+
+    /// Adds a `help: ...` line, printed after all caret blocks
+    pub fn with_help<I: Display>(self, message: I) -> Self {
+        self.with_footer(Severity::Help, message)
+    }
+
+    /// Adds a free-standing message, printed after all caret blocks
+    pub fn with_footer<S: Into<Severity>, I: Display>(mut self, severity: S, message: I) -> Self {
+        self.footers.push((severity.into(), message.to_string()));
+        self
+    }
+
+    pub fn max_footer_length(mut self, length: usize) -> Self {
+        self.max_footer_length = length;
+        self
+    }
+
+    /// Names the first source, so that the location of its first label is printed in the header
+    pub fn with_source_name<I: Display>(mut self, name: I) -> Self {
+        if let Some(file) = self.sources.get_mut(FileId::default()) {
+            file.name = Some(name.to_string());
+        }
+        self
+    }
+
     pub fn with_range(self) -> Self {
         Self {
             display_range: true,
@@ -332,16 +424,71 @@ impl ReportBuilder {
         }
     }
 
+    pub fn with_line_numbers(self) -> Self {
+        Self {
+            line_numbers: true,
+            ..self
+        }
+    }
+
+    /// Sets the number of columns between two tab stops (defaults to 4)
+    ///
+    /// Tabs in the input are expanded to spaces, so that the carets line up with them
+    pub fn tab_width(mut self, width: usize) -> Self {
+        self.tab_width = width;
+        self
+    }
+
+    /// Replaces all layout settings of the report with the given config
+    ///
+    /// Overrides earlier calls to the layout setters (e.g. `plain_text` or `with_glyphs`), so call it first.
+    pub fn with_render_config(mut self, config: RenderConfig) -> Self {
+        self.render_config = config;
+        self
+    }
+
+    /// Generates the colors of labels without an explicit color from the given palette
+    ///
+    /// Each label gets the color of its index, so the same report is always colored the same.
+    /// Child labels without an explicit color are colored like their parent label.
+    /// Labels are left uncolored by default.
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = Some(palette);
+        self
+    }
+
+    /// Leaves labels without an explicit color uncolored
+    pub fn without_auto_colors(mut self) -> Self {
+        self.palette = None;
+        self
+    }
+
+    /// Converts the colors to the given depth while writing, e.g. the one detected by `ColorDepth::detect`
+    ///
+    /// Defaults to `ColorDepth::TrueColor`, so the output does not depend on where it is written to.
+    pub fn with_color_depth(mut self, depth: ColorDepth) -> Self {
+        self.render_config = self.render_config.color_depth(depth);
+        self
+    }
+
+    /// Draws the carets, arrows and gutter of the report with the given glyph set
+    pub fn with_glyphs(mut self, glyphs: GlyphSet) -> Self {
+        self.render_config = self.render_config.glyphs(glyphs);
+        self
+    }
+
+    /// Unless set explicitly, child labels are wrapped `child_label_padding` columns earlier
     pub fn max_label_length(mut self, length: usize) -> Self {
-        self.max_label_length = length;
-        if self.max_child_label_length.is_none() {
-            self.max_child_label_length = Some(length + CHILD_LABEL_PADDING);
+        let config = &mut self.render_config;
+        config.max_label_length = length;
+        if config.max_child_label_length.is_none() {
+            config.max_child_label_length = Some(length.saturating_sub(config.child_label_padding));
         }
         self
     }
 
     pub fn max_child_label_length(mut self, length: usize) -> Self {
-        self.max_child_label_length = Some(length);
+        self.render_config = self.render_config.max_child_label_length(length);
         self
     }
 
@@ -386,11 +533,15 @@ impl ReportBuilder {
 pub struct Label {
     /// The range in the input string that this label annotates
     pub(super) range: RangeInclusive,
+    /// The source the range points into
+    pub(super) file: FileId,
     /// If no colors is set, it will be generated at runtime
     pub(super) message: String,
     /// Optional child labels for more detailed annotations
     /// or if a message would repeat too much
     pub(super) child_labels: Vec<ChildLabel>,
+    /// Suggested replacements, printed as a patched preview after the caret blocks
+    pub(super) suggestions: Vec<Suggestion>,
     pub(super) color: Option<Vec<AnsiStyle>>,
     #[cfg(feature = "caret_color")]
     pub(super) caret_color: Option<RgbColor>,
@@ -399,14 +550,24 @@ impl Label {
     pub fn new<I: Display, R: IntoRange>(range: R, message: I) -> Self {
         Self {
             range: range.into_range(),
+            file: FileId::default(),
             message: message.to_string(),
             child_labels: Vec::new(),
+            suggestions: Vec::new(),
             color: None,
             #[cfg(feature = "caret_color")]
             caret_color: None,
         }
     }
 
+    /// Points the label into another source of the builders `Sources`
+    ///
+    /// The id must have been handed out by those very `Sources`, see `FileId`
+    pub fn in_file(mut self, file: FileId) -> Self {
+        self.file = file;
+        self
+    }
+
     /// Replaces the current message
     pub fn with_message<I: Display>(mut self, message: I) -> Self {
         self.message = message.to_string();
@@ -428,6 +589,22 @@ impl Label {
         self
     }
 
+    /// Suggests replacing the given range (of the labels file) with the given text
+    pub fn with_suggestion<R: IntoRange, I: Display>(mut self, range: R, replacement: I) -> Self {
+        self.suggestions.push(Suggestion::new(range, replacement));
+        self
+    }
+
+    /// Suggests inserting the given text before the given byte offset (of the labels file)
+    pub fn with_insertion<I: Display>(mut self, at: usize, text: I) -> Self {
+        self.suggestions.push(Suggestion::insert(at, text));
+        self
+    }
+
+    pub fn suggestions(&self) -> &[Suggestion] {
+        &self.suggestions
+    }
+
     #[allow(dead_code)]
     pub(crate) fn get_message(&self) -> String {
         let Self { message, color, .. } = self;
@@ -515,6 +692,63 @@ impl ChildLabel {
     }
 }
 
+/// A suggested replacement of a part of the source
+///
+/// Rendered as a "help: try this" block showing the patched lines,
+/// and exposed by `Report::suggestions` so that editors can apply it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Suggestion {
+    /// The source the range points into, taken from the label
+    pub(super) file: FileId,
+    /// The (exclusive) byte range to replace, empty for insertions
+    pub(super) range: std::ops::Range<usize>,
+    pub(super) replacement: String,
+}
+impl Suggestion {
+    pub fn new<R: IntoRange, I: Display>(range: R, replacement: I) -> Self {
+        let range = range.into_range();
+        Self {
+            file: FileId::default(),
+            range: range.start()..range.end().sat_add(1),
+            replacement: replacement.to_string(),
+        }
+    }
+
+    /// Inserts the text before the given byte offset, without replacing anything
+    pub fn insert<I: Display>(at: usize, text: I) -> Self {
+        Self {
+            file: FileId::default(),
+            range: at..at,
+            replacement: text.to_string(),
+        }
+    }
+
+    pub fn file(&self) -> FileId {
+        self.file
+    }
+
+    /// The (exclusive) byte range to replace
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.range.clone()
+    }
+
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+}
+
+/// The labels of a single source line, collected while generating the report
+#[derive(Debug, Default)]
+struct LineLabels {
+    /// Labels contained within the line, with their range within the whole source
+    labels: Vec<(Label, RangeInclusive)>,
+    /// Labels spanning onto the line, with the line they start on, their (tab expanded) offset within
+    /// that line and their range within the whole source
+    spans: Vec<(usize, usize, Label, RangeInclusive)>,
+    /// All labels covering (parts of) the line, used for trimming
+    hints: Vec<Label>,
+}
+
 #[derive(Debug, thiserror::Error, PartialEq, Eq)]
 pub enum Error {
     #[error("No labels were added to the report")]
@@ -525,6 +759,13 @@ pub enum Error {
     LabelChildEmptyMessage,
     #[error("The input string is empty")]
     EmptyInput,
+    #[error("A label points into an unknown file: {0:?}")]
+    UnknownFile(FileId),
+    #[error("A suggestion has a range that is out of bounds or splits a character: {0:?}")]
+    SuggestionOutOfBounds(std::ops::Range<usize>),
+    /// Overlapping suggestions could neither be previewed nor applied together
+    #[error("Two suggestions replace overlapping ranges: {0:?} and {1:?}")]
+    OverlappingSuggestions(std::ops::Range<usize>, std::ops::Range<usize>),
     /// (given range, valid range)
     #[error(
         "A label has a range that is out of bounds: {valid:?} not contained within {attempted_range:?}"
@@ -536,7 +777,26 @@ pub enum Error {
     },
 }
 
-impl ReportBuilder {
+/// The labels pointing into a single source, along with the source they were resolved to while validating
+#[derive(Debug)]
+struct FileLabels<'a, 'src> {
+    file: &'a SourceFile<'src>,
+    /// Indexing the lines is the only pass over a whole source, the rest only touches the printed lines
+    lines: SourceLines<'a>,
+    /// With their index within the builder, which is the order of the summary
+    labels: Vec<(usize, Label)>,
+}
+impl<'a, 'src> FileLabels<'a, 'src> {
+    fn new(file: &'a SourceFile<'src>) -> Self {
+        Self {
+            file,
+            lines: SourceLines::new(&file.input),
+            labels: Vec::new(),
+        }
+    }
+}
+
+impl ReportBuilder<'_> {
     /// Validates the current state of the builder and generates the Report.
     /// Returns a BuilderError if the state is invalid, but does not consume Self.
     /// This allows one to fix the issues and try again without reinstantiating
@@ -546,68 +806,411 @@ impl ReportBuilder {
         if self.labels.is_empty() {
             return Err(Error::NoLabels);
         }
-        if self.input.is_empty() {
-            return Err(Error::EmptyInput);
-        }
-
-        let valid_range = 0..=self.input.len();
         #[cfg(feature = "truncate_out_of_bounds")]
         let mut needs_truncate = false;
-        self.labels.iter().try_for_each(|label| {
-            if label.range.start() < *valid_range.start() || label.range.end() > *valid_range.end()
-            {
-                #[cfg(not(feature = "truncate_out_of_bounds"))]
-                {
-                    return Err(Error::OutOfBounds {
-                        attempted_range: label.range.clone(),
-                        valid: valid_range.clone().into(),
-                    });
+        // Every file renders in its own section, the first one carrying the severity, code and title
+        let mut files: BTreeMap<FileId, FileLabels> = BTreeMap::new();
+        self.labels
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, label)| {
+                let file = self
+                    .sources
+                    .get(label.file)
+                    .ok_or(Error::UnknownFile(label.file))?;
+                let input = &file.input;
+                if input.is_empty() {
+                    return Err(Error::EmptyInput);
                 }
-                #[cfg(feature = "truncate_out_of_bounds")]
+                let valid_range = 0..=input.len();
+                if label.range.start() < *valid_range.start()
+                    || label.range.end() > *valid_range.end()
                 {
-                    needs_truncate = true;
+                    #[cfg(not(feature = "truncate_out_of_bounds"))]
+                    {
+                        return Err(Error::OutOfBounds {
+                            attempted_range: label.range,
+                            valid: valid_range.clone().into(),
+                        });
+                    }
+                    #[cfg(feature = "truncate_out_of_bounds")]
+                    {
+                        needs_truncate = true;
+                    }
                 }
-            }
-            if label.message.is_empty() {
-                return Err(Error::LabelEmptyMessage);
-            }
-            label.child_labels.iter().try_for_each(|child_label| {
-                if child_label.message.is_empty() {
-                    return Err(Error::LabelChildEmptyMessage);
+                if label.message.is_empty() {
+                    return Err(Error::LabelEmptyMessage);
                 }
+                label.child_labels.iter().try_for_each(|child_label| {
+                    if child_label.message.is_empty() {
+                        return Err(Error::LabelChildEmptyMessage);
+                    }
+                    Ok(())
+                })?;
+                // Suggestions are never truncated, as a partial replacement would be wrong
+                label.suggestions.iter().try_for_each(|suggestion| {
+                    let range = &suggestion.range;
+                    if range.start > range.end
+                        || !input.is_char_boundary(range.start)
+                        || !input.is_char_boundary(range.end)
+                    {
+                        return Err(Error::SuggestionOutOfBounds(range.clone()));
+                    }
+                    Ok(())
+                })?;
+
+                // Labels are colored by their index, before they are split up by file
+                let label = match (&label.color, self.palette) {
+                    (None, Some(palette)) => {
+                        let color = AnsiStyle::RgbColor(palette.color(index));
+                        let mut label = label.clone().with_color(color);
+                        label
+                            .child_labels
+                            .iter_mut()
+                            .filter(|child| child.color.is_none())
+                            .for_each(|child| child.color = Some(vec![color]));
+                        label
+                    }
+                    _ => label.clone(),
+                };
+                files
+                    .entry(label.file)
+                    .or_insert_with(|| FileLabels::new(file))
+                    .labels
+                    .push((index, label));
                 Ok(())
             })?;
-            Ok(())
+        files.values().try_for_each(|FileLabels { labels, .. }| {
+            let mut ranges = labels
+                .iter()
+                .flat_map(|(_, label)| label.suggestions.iter().map(|suggestion| &suggestion.range))
+                .collect::<Vec<_>>();
+            // Sorted by start, so a range can only overlap the one before it (if any overlaps)
+            ranges.sort_by_key(|range| (range.start, range.end));
+            ranges.windows(2).try_for_each(|pair| match pair {
+                [previous, next] if previous.end > next.start => Err(
+                    Error::OverlappingSuggestions((*previous).clone(), (*next).clone()),
+                ),
+                _ => Ok(()),
+            })
         })?;
 
-        // Offset to apply to label ranges when trimming the input
-        let mut input_label_offset = 0;
+        let sections = files
+            .iter()
+            .enumerate()
+            .map(
+                |(
+                    index,
+                    (
+                        &id,
+                        FileLabels {
+                            file,
+                            lines,
+                            labels,
+                        },
+                    ),
+                )| {
+                    let labels = labels.iter().map(|(_, label)| label).collect::<Vec<_>>();
+                    // The location is taken from the start of the first label, just like its summary
+                    let location = file.name.as_ref().map(|name| {
+                        Location::new(name, Position::new(lines, labels[0].range.start()))
+                    });
+                    let header = if index == 0 {
+                        Header::new(
+                            self.severity.clone(),
+                            self.code.clone(),
+                            self.title.clone(),
+                            location,
+                        )
+                    } else {
+                        Header::new(None, None, None, location)
+                    };
+                    // Suggestions always point into the file of their label
+                    let suggestions = labels
+                        .iter()
+                        .flat_map(|label| {
+                            label.suggestions.iter().map(|suggestion| Suggestion {
+                                file: id,
+                                ..suggestion.clone()
+                            })
+                        })
+                        .collect();
+                    Section::new(
+                        header,
+                        self.generate_lines(
+                            lines,
+                            &labels,
+                            #[cfg(feature = "truncate_out_of_bounds")]
+                            needs_truncate,
+                        ),
+                    )
+                    .with_suggestions(lines, suggestions, self.tab_width)
+                },
+            )
+            .collect();
 
-        let input = if self.trim_input != Trim::None {
-            let (trimmed_input, offset) = Report::trim_input(
-                &self.input,
-                self.labels.iter(),
-                self.trim_input,
-                #[cfg(feature = "truncate_out_of_bounds")]
-                {
-                    needs_truncate && self.truncate_out_of_bounds as u8 != 0
+        Ok(Report::new(
+            sections,
+            self.footers
+                .iter()
+                .map(|(severity, message)| {
+                    Footer::new(
+                        severity.clone(),
+                        LineTokenStream::from_str_with_length(message, self.max_footer_length),
+                    )
+                })
+                .collect(),
+            self.display_range,
+            self.line_numbers,
+            self.colored_input,
+            self.render_config,
+        )
+        .with_summary(self.summary(&files)))
+    }
+
+    /// Collects the labels and messages with their positions, using the original (untrimmed) sources
+    fn summary(&self, files: &BTreeMap<FileId, FileLabels>) -> ReportSummary {
+        let mut labels = files
+            .values()
+            .flat_map(
+                |FileLabels {
+                     file,
+                     lines,
+                     labels,
+                 }| {
+                    labels.iter().map(|(index, label)| {
+                        let end = label.range.end().sat_add(1);
+                        let summary = LabelSummary {
+                            source: file.name.clone(),
+                            range: label.range.start()..end,
+                            start: Position::new(lines, label.range.start()),
+                            end: Position::new(lines, end),
+                            message: strip_styles(&label.message),
+                            children: label
+                                .child_labels
+                                .iter()
+                                .map(|child| strip_styles(&child.message))
+                                .collect(),
+                        };
+                        (*index, summary)
+                    })
                 },
-            );
-            input_label_offset = offset;
-            trimmed_input
+            )
+            .collect::<Vec<_>>();
+        // Back into the order the labels were added in
+        labels.sort_by_key(|(index, _)| *index);
+        ReportSummary {
+            severity: self.severity.clone().unwrap_or_default(),
+            code: self.code.clone(),
+            title: self.title.as_deref().map(strip_styles),
+            labels: labels.into_iter().map(|(_, label)| label).collect(),
+            footers: self
+                .footers
+                .iter()
+                .map(|(severity, message)| FooterSummary {
+                    severity: severity.clone(),
+                    message: strip_styles(message),
+                })
+                .collect(),
+        }
+    }
+
+    /// Rebases the labels of a single file onto the lines they annotate
+    /// and trims and tokenizes these lines
+    fn generate_lines(
+        &self,
+        source: &SourceLines,
+        labels: &[&Label],
+        #[cfg(feature = "truncate_out_of_bounds")] needs_truncate: bool,
+    ) -> Vec<SectionLine> {
+        // Group the labels by the line their range ends on,
+        // with the ranges rebased to be relative to the start of that line.
+        // Labels spanning multiple lines are annotated on their last line, remembering their first line.
+        // Every line also gets the ranges it is covered by, so that trimming never hides parts of them.
+        let mut line_labels: BTreeMap<usize, LineLabels> = BTreeMap::new();
+        labels.iter().for_each(|label| {
+            let (first, last) = source.span(label.range);
+            if first == last {
+                let (line, range) = source.rebase(label.range);
+                let rebased = Label {
+                    range,
+                    ..(*label).clone()
+                };
+                let entry = line_labels.entry(line).or_default();
+                entry.hints.push(rebased.clone());
+                entry.labels.push((rebased, label.range));
+                return;
+            }
+            (first..=last).for_each(|line| {
+                let line_range = source.line_range(line);
+                let start = label.range.start().max(line_range.start);
+                let end = label.range.end().min(line_range.end);
+                let entry = line_labels.entry(line).or_default();
+                let rebased = Label {
+                    range: (start.sat_sub(line_range.start)..=end.sat_sub(line_range.start)).into(),
+                    ..(*label).clone()
+                };
+                entry.hints.push(rebased.clone());
+                if line == last {
+                    let start = label.range.start().sat_sub(source.line_start(first));
+                    let start = ExpandedLine::new(source.line(first), self.tab_width).offset(start);
+                    entry.spans.push((first, start, rebased, label.range));
+                }
+            });
+        });
+
+        line_labels
+            .into_iter()
+            .map(
+                |(
+                    line,
+                    LineLabels {
+                        labels,
+                        spans,
+                        hints,
+                    },
+                )| {
+                    // The terminal would expand tabs on its own, not caring about the label ranges
+                    let expanded = ExpandedLine::new(source.line(line), self.tab_width);
+                    let expand = |label: &Label| Label {
+                        range: expanded.range(label.range),
+                        ..label.clone()
+                    };
+                    let labels = labels
+                        .iter()
+                        .map(|(label, source_range)| (expand(label), *source_range))
+                        .collect::<Vec<_>>();
+                    let spans = spans
+                        .iter()
+                        .map(|(first, start, label, source_range)| {
+                            (*first, *start, expand(label), *source_range)
+                        })
+                        .collect::<Vec<_>>();
+                    let hints = hints.iter().map(expand).collect::<Vec<_>>();
+                    let line_input = expanded.text();
+
+                    // Offset to apply to label ranges when trimming the input
+                    let mut input_label_offset = 0;
+
+                    let input = if self.trim_input != Trim::None {
+                        let (trimmed_input, offset) = Report::trim_input(
+                            line_input,
+                            hints.iter(),
+                            self.trim_input,
+                            #[cfg(feature = "truncate_out_of_bounds")]
+                            {
+                                needs_truncate && self.truncate_out_of_bounds as u8 != 0
+                            },
+                        );
+                        input_label_offset = offset;
+                        trimmed_input
+                    } else {
+                        line_input.to_string()
+                    };
+
+                    // `with_range` prints the range the caller gave, not the rebased one
+                    let tokenize = |label: &Label, source_range: RangeInclusive| {
+                        self.tokenize_label(
+                            label,
+                            #[cfg(feature = "truncate_out_of_bounds")]
+                            line_input.len(),
+                        )
+                        .with_source_range(source_range)
+                    };
+                    let labels = labels
+                        .iter()
+                        .map(|(label, source_range)| tokenize(label, *source_range))
+                        .collect::<Vec<_>>();
+                    let spans = spans
+                        .iter()
+                        .map(|(first, start, label, source_range)| {
+                            (*first, *start, tokenize(label, *source_range))
+                        })
+                        .collect::<Vec<_>>();
+                    (line, input, input_label_offset, labels, spans)
+                },
+            )
+            .collect()
+    }
+
+    /// Tokenizes the message and child labels of a label,
+    /// whose range has already been rebased onto its line
+    fn tokenize_label(
+        &self,
+        label: &Label,
+        #[cfg(feature = "truncate_out_of_bounds")] line_len: usize,
+    ) -> TokenizedLabelFull {
+        #[cfg(feature = "caret_color")]
+        let label_caret_color: Option<RgbColor> = if self.caret_color {
+            label.caret_color.or_else(|| {
+                // If the label has no caret color, use the first color of the label if it exists
+                label.color.as_ref().and_then(|colors| {
+                    if colors.is_empty() {
+                        None
+                    } else {
+                        if let Ok(rbg_color) = RgbColor::try_from(colors[0]) {
+                            Some(rbg_color)
+                        } else {
+                            None
+                        }
+                    }
+                })
+            })
         } else {
-            self.input.clone()
+            None
         };
 
-        let labels = self
-            .labels
-            .iter()
-            .map(|label| {
+        let stream = {
+            let mut stream = LineTokenStream::from_str_with_length(
+                &label.message,
+                self.render_config.max_label_length,
+            );
+            if let Some(color) = &label.color {
+                color.into_iter().for_each(|c| {
+                    stream.on_color_all(*c);
+                });
+            }
+            stream
+        };
+        #[cfg(feature = "truncate_out_of_bounds")]
+        // Avoids needing mut if the feature is not enabled
+        let mut stream = stream;
+
+        #[cfg(not(feature = "truncate_out_of_bounds"))]
+        // Not need for mut here
+        let range = label.range;
+        #[cfg(feature = "truncate_out_of_bounds")]
+        let mut range = label.range;
+        #[cfg(feature = "truncate_out_of_bounds")]
+        {
+            // Make sure to call "truncate_end" first, as it changes the range
+            // if its out of bounds
+            if range.truncate_end(line_len) && self.truncate_out_of_bounds == TruncateMode::Indicate
+            {
+                use ::token::{Token, TokenStream};
+
+                stream.insert_line(
+                    0,
+                    TokenStream::from_iter(
+                        [Token::Styled(
+                            AnsiStyle::BRIGHT_YELLOW,
+                            Some(Box::new(Token::Literal("[ Label Range Truncated ]".into()))),
+                        )]
+                        .into_iter(),
+                    ),
+                );
+            }
+        }
+
+        TokenizedLabelFull::new_from(
+            range,
+            stream,
+            label.child_labels.clone().into_iter().map(|cl| {
                 #[cfg(feature = "caret_color")]
-                let label_caret_color: Option<RgbColor> = if self.caret_color {
-                    label.caret_color.or_else(|| {
+                let child_caret_color = if self.caret_color {
+                    cl.caret_color.or_else(|| {
                         // If the label has no caret color, use the first color of the label if it exists
-                        label.color.as_ref().and_then(|colors| {
+                        cl.color.as_ref().and_then(|colors| {
                             if colors.is_empty() {
                                 None
                             } else {
@@ -623,105 +1226,26 @@ impl ReportBuilder {
                     None
                 };
 
-                let stream = {
-                    let mut stream = LineTokenStream::from_str_with_length(
-                        &label.message,
-                        self.max_label_length,
-                    );
-                    if let Some(color) = &label.color {
-                        color.into_iter().for_each(|c| {
-                            stream.on_color_all(*c);
-                        });
-                    }
-                    stream
-                };
-                #[cfg(feature = "truncate_out_of_bounds")]
-                // Avoids needing mut if the feature is not enabled
-                let mut stream = stream;
-
-                #[cfg(not(feature = "truncate_out_of_bounds"))]
-                // Not need for mut here
-                let range = label.range;
-                #[cfg(feature = "truncate_out_of_bounds")]
-                let mut range = label.range;
-                #[cfg(feature = "truncate_out_of_bounds")]
-                {
-                    // Make sure to call "truncate_end" first, as it changes the range
-                    // if its out of bounds
-                    if range.truncate_end(*valid_range.end())
-                        && self.truncate_out_of_bounds == TruncateMode::Indicate
+                TokenizedChildLabel::new_from(
                     {
-                        use ::token::{Token, TokenStream};
-
-                        stream.insert_line(
-                            0,
-                            TokenStream::from_iter(
-                                [Token::Styled(
-                                    AnsiStyle::BRIGHT_YELLOW,
-                                    Some(Box::new(Token::Literal(
-                                        "[ Label Range Truncated ]".into(),
-                                    ))),
-                                )]
-                                .into_iter(),
-                            ),
+                        let mut stream = LineTokenStream::from_str_with_length(
+                            &cl.message,
+                            self.render_config.child_label_length(),
                         );
-                    }
-                }
-
-                TokenizedLabelFull::new_from(
-                    range,
-                    stream,
-                    label.child_labels.clone().into_iter().map(|cl| {
-                        #[cfg(feature = "caret_color")]
-                        let child_caret_color = if self.caret_color {
-                            cl.caret_color.or_else(|| {
-                                // If the label has no caret color, use the first color of the label if it exists
-                                cl.color.as_ref().and_then(|colors| {
-                                    if colors.is_empty() {
-                                        None
-                                    } else {
-                                        if let Ok(rbg_color) = RgbColor::try_from(colors[0]) {
-                                            Some(rbg_color)
-                                        } else {
-                                            None
-                                        }
-                                    }
-                                })
-                            })
-                        } else {
-                            None
-                        };
-
-                        TokenizedChildLabel::new_from(
-                            {
-                                let mut stream = LineTokenStream::from_str_with_length(
-                                    &cl.message,
-                                    self.max_child_label_length
-                                        .unwrap_or(self.max_label_length - CHILD_LABEL_PADDING),
-                                );
-                                if let Some(color) = cl.color {
-                                    color.into_iter().for_each(|c| {
-                                        stream.on_color_all(c);
-                                    });
-                                }
-                                stream
-                            },
-                            #[cfg(feature = "caret_color")]
-                            child_caret_color,
-                        )
-                    }),
+                        if let Some(color) = cl.color {
+                            color.into_iter().for_each(|c| {
+                                stream.on_color_all(c);
+                            });
+                        }
+                        stream
+                    },
                     #[cfg(feature = "caret_color")]
-                    label_caret_color,
+                    child_caret_color,
                 )
-            })
-            .collect::<Vec<_>>();
-        Ok(Report::new(
-            input,
-            input_label_offset,
-            self.display_range,
-            labels,
-            self.colored_input,
-        ))
+            }),
+            #[cfg(feature = "caret_color")]
+            label_caret_color,
+        )
     }
 }
 
@@ -747,3 +1271,100 @@ fn srcbuilderrs466a325e875e424bbbc4474ff8735c3a() {
     dbg!(&stream);
     dbg!(format!("{:#}", stream));
 }
+
+#[test]
+fn label_in_unknown_file() {
+    let mut sources = Sources::new();
+    let file = sources.add("a.toml", "[package]");
+    let mut report = ReportBuilder::from_sources(sources);
+    report.push(Label::new(1..=7, "Known file").in_file(file));
+    assert!(report.finish().is_ok());
+    // Ids are only valid for the registry that handed them out
+    let mut other = Sources::new();
+    other.add("b.toml", "[package]");
+    let unknown = other.add("c.toml", "[package]");
+    report.push(Label::new(1..=7, "Unknown file").in_file(unknown));
+    assert_eq!(report.finish().err(), Some(Error::UnknownFile(unknown)));
+}
+
+#[test]
+fn summary_keeps_label_order_across_files() {
+    let mut sources = Sources::new();
+    sources.add("main.rs", "mod lib;");
+    let lib = sources.add("lib.rs", "pub fn f() {}");
+    let mut report = ReportBuilder::from_sources(sources);
+    report.push(Label::new(7..=7, "defined here").in_file(lib));
+    report.push(Label::new(4..=6, "declared here"));
+    let report = report.finish().unwrap();
+    let sources = report
+        .summary()
+        .labels
+        .iter()
+        .map(|label| label.source.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(sources, [Some("lib.rs"), Some("main.rs")]);
+    // The sections are ordered by file though
+    let output = report.to_string();
+    assert!(output.find("main.rs").unwrap() < output.find("lib.rs").unwrap());
+}
+
+#[test]
+fn auto_colors_are_deterministic() {
+    let render = || {
+        let mut report = ReportBuilder::new("let x = y;")
+            .trim_input(Trim::None)
+            .with_palette(Palette::default());
+        report.push(Label::new(4..=5, "first").with_child_label(ChildLabel::new("child")));
+        report.push(Label::new(8..=9, "second").with_color(AnsiStyle::RED));
+        let mut output = Vec::new();
+        report.finish().unwrap().write(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    let output = render();
+    assert_eq!(output, render());
+    assert!(output.contains(&Palette::default().color(0).to_ansi_escape_sequence()));
+    // Explicit colors are kept, thus the palette color of the second label is never used
+    assert!(!output.contains(&Palette::default().color(1).to_ansi_escape_sequence()));
+    // Child labels are colored like their parent
+    let child = output.find("child").unwrap();
+    let color = Palette::default().color(0).to_ansi_escape_sequence();
+    assert!(output[..child].ends_with(&color), "{output:?}");
+}
+
+#[test]
+fn borrowed_and_shared_sources() {
+    let log = (0..1000)
+        .map(|i| format!("line {i}: ok\n"))
+        .collect::<String>();
+    let render = |report: ReportBuilder| {
+        let mut report = report.trim_input(Trim::None);
+        report.push(Label::new(11600..=11601, "not ok"));
+        let mut output = Vec::new();
+        report.finish().unwrap().write(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    let owned = render(ReportBuilder::new(log.clone()));
+    // The builder only borrows the log, the report owns nothing but the printed line
+    let borrowed = ReportBuilder::new(log.as_str());
+    assert!(matches!(
+        borrowed.sources.get(FileId::default()).unwrap().input,
+        SourceText::Borrowed(_)
+    ));
+    assert_eq!(render(borrowed), owned);
+    let shared: std::sync::Arc<str> = log.into();
+    assert_eq!(render(ReportBuilder::new(shared)), owned);
+    assert!(owned.contains("line 900: ok"));
+}
+
+#[test]
+fn child_labels_wrap_shorter() {
+    let report = ReportBuilder::new("").max_label_length(20);
+    assert_eq!(report.render_config.child_label_length(), 16);
+    // Does not underflow with a padding wider than the labels
+    let report = ReportBuilder::new("").max_label_length(2);
+    assert_eq!(report.render_config.child_label_length(), 0);
+    let report = ReportBuilder::new("")
+        .max_child_label_length(10)
+        .max_label_length(20);
+    assert_eq!(report.render_config.child_label_length(), 10);
+}
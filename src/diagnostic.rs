@@ -0,0 +1,96 @@
+use crate::{ReportBuilder, SourceText};
+
+/// A value (usually an error) that knows how to annotate the source it refers to
+///
+/// Usually derived with `#[derive(Diagnostic)]` (`derive` feature), see there for the attributes.
+pub trait Diagnostic {
+    /// Creates a builder annotating the input, to be finished (or extended) by the caller
+    fn report<'src, I: Into<SourceText<'src>>>(&self, input: I) -> ReportBuilder<'src>;
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn derived_diagnostic_builds_labels() {
+    use crate::{Diagnostic, Severity};
+
+    #[derive(Debug, Diagnostic)]
+    #[severity(warning)]
+    enum ParseError {
+        #[child("numbers may not start with a letter")]
+        Expected {
+            #[label("expected {expected}")]
+            #[child("found {found:?}")]
+            span: std::ops::Range<usize>,
+            expected: &'static str,
+            found: char,
+        },
+        #[severity(error)]
+        Unclosed(
+            #[label("opened here")] std::ops::RangeInclusive<usize>,
+            #[label("expected `{_2}` before the end")] usize,
+            char,
+        ),
+    }
+
+    let render = |error: ParseError| {
+        let report = error.report("let x = (a1");
+        report.plain_text(true).finish().unwrap().to_string()
+    };
+    let expected = ParseError::Expected {
+        span: 9..10,
+        expected: "a number",
+        found: 'a',
+    };
+    let report = expected.report("let x = (a1").finish().unwrap();
+    assert_eq!(report.summary().severity, Severity::Warning);
+    let label = &report.summary().labels[0];
+    assert_eq!(label.range, 9..10);
+    assert_eq!(label.message, "expected a number");
+    assert_eq!(
+        label.children,
+        ["found 'a'", "numbers may not start with a letter"]
+    );
+    assert!(render(expected).contains("expected a number"));
+
+    let unclosed = ParseError::Unclosed(8..=8, 10, ')');
+    let report = unclosed.report("let x = (a1").finish().unwrap();
+    assert_eq!(report.summary().severity, Severity::Error);
+    let messages = report
+        .summary()
+        .labels
+        .iter()
+        .map(|label| label.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["opened here", "expected `)` before the end"]);
+    assert!(render(unclosed).contains("opened here"));
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn derived_fields_do_not_shadow_the_builder() {
+    use crate::Diagnostic;
+
+    #[derive(Debug, Diagnostic)]
+    struct Duplicate {
+        #[label("first defined here")]
+        report: std::ops::Range<usize>,
+        #[label("redefined here, at {input:?}")]
+        input: std::ops::Range<usize>,
+    }
+
+    let duplicate = Duplicate {
+        report: 4..5,
+        input: 15..16,
+    };
+    let report = duplicate.report("let x = 1; let x = 2;").finish().unwrap();
+    let messages = report
+        .summary()
+        .labels
+        .iter()
+        .map(|label| label.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        ["first defined here", "redefined here, at 15..16"]
+    );
+}
@@ -2,8 +2,16 @@
 #![deny(dead_code, unused)]
 
 mod builder;
+mod diagnostic;
 mod printer;
+mod severity;
+mod source;
+mod spanned;
+mod summary;
 use printer::*;
+// The derived implementations refer to `::reporter`, which has to resolve within the crate as well
+#[cfg(all(test, feature = "derive"))]
+extern crate self as reporter;
 /// A module for finding patterns in text
 ///
 /// Provides a forward and backward iterator for finding substrings
@@ -13,13 +21,38 @@ use printer::*;
 pub mod find_iter;
 pub(crate) use find_iter::*;
 
-pub use ::token::{AnsiStyle, Color, RgbColor, Style, impl_field};
-pub use builder::{ChildLabel, Error, IntoRange, Label, RangeInclusive, ReportBuilder, Trim, TrimPadding};
+pub use ::token::{AnsiStyle, Color, ColorDepth, GlyphSet, Palette, RgbColor, Style, impl_field};
+pub use severity::Severity;
+pub use source::{FileId, SourceFile, SourceText, Sources};
+pub use spanned::{ErrorChain, Spanned};
+pub use diagnostic::Diagnostic;
+#[cfg(feature = "derive")]
+pub use reporter_derive::Diagnostic;
+pub use summary::{FooterSummary, LabelSummary, Position, ReportSummary};
+pub use builder::{ChildLabel, Error, IntoRange, Label, RangeInclusive, ReportBuilder, Suggestion, Trim, TrimPadding};
+pub use printer::RenderConfig;
+/// Compatibility shim for the former global layout settings, see `RenderConfig`
 pub mod config {
     use super::printer;
-    pub use printer::set_arrow_label_padding;
-    pub use printer::set_child_label_offset;
+    pub use printer::RenderConfig;
+
+    /// Only affects reports whose builder is created afterwards
+    #[deprecated(
+        note = "Use `RenderConfig::arrow_label_padding` instead, this only affects builders created afterwards"
+    )]
+    pub fn set_arrow_label_padding(padding: usize) {
+        printer::set_default_arrow_label_padding(padding);
+    }
+    /// Only affects reports whose builder is created afterwards
+    #[deprecated(
+        note = "Use `RenderConfig::child_label_offset` instead, this only affects builders created afterwards"
+    )]
+    pub fn set_child_label_offset(offset: usize) {
+        printer::set_default_child_label_offset(offset);
+    }
 }
 
 #[cfg(feature = "truncate_out_of_bounds")]
-pub use builder::TruncateMode;
\ No newline at end of file
+pub use builder::TruncateMode;
+#[cfg(feature = "serde")]
+pub use printer::SarifLog;
\ No newline at end of file
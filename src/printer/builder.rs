@@ -3,31 +3,180 @@ use ::std::str::FromStr;
 use ::token::saturating::SaturatingArithmetic;
 
 use super::*;
+use crate::source::SourceLines;
 /// The final report that can be printed to the user
 /// Contained labels are printed each on their own
 #[derive(Debug)]
 pub struct Report {
     pub(crate) display_range: bool,
-    pub(super) input: TokenStream,
+    /// Only set if line numbers should be displayed
+    ///
+    /// Shared by all sections, so that they line up with each other
+    pub(super) gutter: Option<Gutter>,
     pub(super) colored_input: bool,
-    pub(super) report_labels: ReportLabels,
+    /// Layout settings used to format the carets
+    pub(super) config: RenderConfig,
+    /// One section per source file referenced by the labels
+    pub(super) sections: Vec<Section>,
+    /// Printed once after all sections
+    pub(super) footers: Vec<Footer>,
+    /// The labels and messages the report was built from
+    pub(super) summary: ReportSummary,
 }
 impl Report {
-    pub fn new<'a, I: Into<String>>(
-        input: I,
-        offset: usize,
+    pub fn new(
+        sections: Vec<Section>,
+        footers: Vec<Footer>,
         display_range: bool,
-        labels: impl IntoIterator<Item = TokenizedLabelFull>,
+        line_numbers: bool,
         colored_input: bool,
+        config: RenderConfig,
     ) -> Self {
-        let input = input.into();
+        let gutter = line_numbers.then(|| {
+            Gutter::new(
+                sections
+                    .iter()
+                    .flat_map(|section| section.lines.iter())
+                    .map(|line| line.number())
+                    .max()
+                    .unwrap_or(1),
+            )
+        });
         Self {
             display_range,
-            input: TokenStream::from(&input),
+            gutter,
             colored_input,
-            report_labels: Self::generate_underbar(offset, labels),
+            config,
+            sections: sections
+                .into_iter()
+                .map(|section| Section {
+                    header: section.header.map(|header| header.with_gutter(gutter)),
+                    patches: section
+                        .patches
+                        .into_iter()
+                        .map(|patch| patch.with_gutter(gutter))
+                        .collect(),
+                    ..section
+                })
+                .collect(),
+            footers: footers
+                .into_iter()
+                .map(|footer| footer.with_gutter(gutter))
+                .collect(),
+            summary: ReportSummary::default(),
+        }
+    }
+
+    pub(crate) fn with_summary(mut self, summary: ReportSummary) -> Self {
+        self.summary = summary;
+        self
+    }
+
+    /// The labels and messages of the report, with their positions in the sources
+    pub fn summary(&self) -> &ReportSummary {
+        &self.summary
+    }
+
+    /// All suggestions of the report, e.g. to be applied by an editor
+    pub fn suggestions(&self) -> impl Iterator<Item = &Suggestion> {
+        self.sections
+            .iter()
+            .flat_map(|section| section.suggestions.iter())
+    }
+}
+
+/// A single source line to be printed, given as its (0-based) index within the source,
+/// its (possibly trimmed) input, the offset induced by trimming, the labels annotating it
+/// and the labels spanning onto it (with the index of the line they start on and their offset within it)
+pub type SectionLine = (
+    usize,
+    String,
+    usize,
+    Vec<TokenizedLabelFull>,
+    Vec<(usize, usize, TokenizedLabelFull)>,
+);
+
+/// The part of a report annotating a single source file
+#[derive(Debug)]
+pub struct Section {
+    /// Printed before the caret blocks if set
+    pub(super) header: Option<Header>,
+    /// The (possibly trimmed) source lines referenced by the labels
+    ///
+    /// Each caret references its line by the index within this vector
+    pub(super) lines: Vec<SourceLine>,
+    pub(super) report_labels: ReportLabels,
+    /// Suggestions of the labels pointing into this file
+    pub(super) suggestions: Vec<Suggestion>,
+    /// Previews of the suggestions, printed after the caret blocks
+    pub(super) patches: Vec<Patch>,
+}
+impl Section {
+    /// As spanning labels reference the line they start on,
+    /// all lines in between must be given as well
+    pub fn new(header: Option<Header>, lines: impl IntoIterator<Item = SectionLine>) -> Self {
+        let mut input_lines = Vec::new();
+        // The input and trimming offset of every line, to find the column a span starts at
+        let mut trimmed: Vec<(String, usize)> = Vec::new();
+        let mut carets = Vec::new();
+        for (index, (line, input, offset, labels, spans)) in lines.into_iter().enumerate() {
+            carets.extend(Report::generate_underbar(index, &input, offset, labels));
+            trimmed.push((input.clone(), offset));
+            carets.extend(spans.into_iter().flat_map(|(first, start, label)| {
+                // As all lines in between are present, the index of the first line is just an offset away
+                let first = index.sat_sub(line.sat_sub(first));
+                let (first_input, first_offset) = &trimmed[first];
+                let column =
+                    width::columns_of(first_input, underbar::rebase_trimmed(*first_offset, start))
+                        .start;
+                Report::generate_underbar(index, &input, offset, [label])
+                    .into_iter()
+                    .map(move |caret| caret.spanning_from(first, column))
+            }));
+            input_lines.push(SourceLine::new(line, input));
+        }
+        Self {
+            header,
+            lines: input_lines,
+            report_labels: carets.into_iter().collect(),
+            suggestions: Vec::new(),
+            patches: Vec::new(),
+        }
+    }
+
+    /// The suggestions must point into the given source,
+    /// whose tabs are expanded to the given width just like the lines of the caret blocks
+    pub(crate) fn with_suggestions(
+        mut self,
+        source: &SourceLines,
+        suggestions: Vec<Suggestion>,
+        tab_width: usize,
+    ) -> Self {
+        self.patches = Patch::generate(source, &suggestions, tab_width);
+        self.suggestions = suggestions;
+        self
+    }
+}
+
+/// A single line of the source that is referenced by at least one label
+#[derive(Debug, Clone)]
+pub struct SourceLine {
+    /// The (0-based) index of the line within the source
+    pub(super) line: usize,
+    /// The (possibly trimmed) text of the line
+    pub(super) input: TokenStream,
+}
+impl SourceLine {
+    pub fn new<I: Into<String>>(line: usize, input: I) -> Self {
+        Self {
+            line,
+            input: TokenStream::from(&input.into()),
         }
     }
+    /// The (1-based) line number as displayed to the user
+    pub fn number(&self) -> usize {
+        self.line.sat_add(1)
+    }
 }
 
 impl Report {
@@ -60,6 +209,10 @@ impl Report {
 
         let TrimPadding { front, back } = bounds;
 
+        // Labels may point into the middle of a character, which must not be split
+        let min_start = input.floor_char_boundary(min_start);
+        let max_end = input.floor_char_boundary(max_end);
+
         // Add 1 word of context on each side if possible
 
         let min_start_padded = input[..min_start]
@@ -138,9 +291,15 @@ impl Report {
         let TrimPadding { front, back } = bounds;
 
         // Add `front` chars of context on the left if possible
-        let min_start_padded = min_start.saturating_sub(front);
+        // without splitting a character
+        let min_start_padded = input.floor_char_boundary(min_start.saturating_sub(front));
         // Add `back` chars of context on the right if possible
-        let max_end_padded = (max_end + back.sat_add(1)).min(input_len);
+        let max_end_padded = input.ceil_char_boundary(
+            max_end
+                .saturating_add(back)
+                .saturating_add(1)
+                .min(input_len),
+        );
 
         // Ensure we don't go out of bounds
         let trimmed_input = if max_end_padded < input_len {
@@ -207,6 +366,8 @@ impl Report {
 pub struct TokenizedLabelFull {
     /// The range in the input string that this label annotates
     pub(super) range: RangeInclusive,
+    /// The range as given by the caller, before it was rebased onto its line
+    pub(super) source_range: RangeInclusive,
     /// If no colors is set, it will be generated at runtime
     pub(super) message: LineTokenStream,
     #[cfg(feature = "caret_color")]
@@ -224,12 +385,14 @@ impl TokenizedLabelFull {
         message: I,
         #[cfg(feature = "caret_color")] caret_color: Option<RgbColor>,
     ) -> Self {
+        let range = range.into_range();
         let stream =
             LineTokenStream::from_str(&message.to_string()).expect("Failed to parse label message");
         Self {
             #[cfg(feature = "caret_color")]
             caret_color,
-            range: range.into_range(),
+            range,
+            source_range: range,
             message: stream,
             child_labels: Vec::new(),
         }
@@ -240,12 +403,14 @@ impl TokenizedLabelFull {
         child_labels: impl IntoIterator<Item = TokenizedChildLabel>,
         #[cfg(feature = "caret_color")] caret_color: Option<RgbColor>,
     ) -> Self {
+        let range = range.into_range();
         let message =
             LineTokenStream::from_str(&message.to_string()).expect("Failed to parse label message");
         Self {
             #[cfg(feature = "caret_color")]
             caret_color,
-            range: range.into_range(),
+            range,
+            source_range: range,
             message,
             child_labels: child_labels.into_iter().collect(),
         }
@@ -256,16 +421,24 @@ impl TokenizedLabelFull {
         child_labels: impl IntoIterator<Item = TokenizedChildLabel>,
         #[cfg(feature = "caret_color")] caret_color: Option<RgbColor>,
     ) -> Self {
+        let range = range.into_range();
         let message: LineTokenStream = message.into();
         Self {
             #[cfg(feature = "caret_color")]
             caret_color,
-            range: range.into_range(),
+            range,
+            source_range: range,
             message,
             child_labels: child_labels.into_iter().collect(),
         }
     }
 
+    /// Sets the range as given by the caller, if the range has been rebased onto its line
+    pub(crate) fn with_source_range(mut self, range: RangeInclusive) -> Self {
+        self.source_range = range;
+        self
+    }
+
     /// Replaces the current message
     pub fn with_message<I: Display>(mut self, message: I) -> Self {
         self.message =
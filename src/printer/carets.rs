@@ -2,26 +2,9 @@ use ::token::saturating::SaturatingArithmetic;
 
 use super::*;
 
-// We wanna be able to allow one to configure these at runtime
-// thus we use static mut variables here
-/// The amount of white-space padding to add directly after the arrow and before the label message
-static mut ARROR_LABEL_PADDING: usize = 1;
-
-/// The offset from the parent label to the child labels
-static mut CHILD_LABEL_OFFSET: usize = 3;
-
-pub fn set_arrow_label_padding(padding: usize) {
-    unsafe {
-        ARROR_LABEL_PADDING = padding;
-    }
-}
-pub fn set_child_label_offset(offset: usize) {
-    unsafe {
-        CHILD_LABEL_OFFSET = offset;
-    }
-}
-
 crate::impl_field!(
+    ReportCaret,line,usize;
+    ReportCaret,span,Option<(usize, usize)>;
     ReportCaret,start,usize;
     ReportCaret,end,usize;
     ReportCaret,rev_positions,Vec<ReportLabel>;
@@ -39,7 +22,6 @@ impl Lines {
     pub fn to_inner<'a>(&'a self) -> impl Iterator<Item = TokenStream> {
         self.lines.iter().map(|l| l.clone().into_inner())
     }
-    #[allow(dead_code)]
     pub fn into_inner(self) -> impl Iterator<Item = TokenStream> {
         self.lines.into_iter().map(|l| l.into_inner())
     }
@@ -77,6 +59,27 @@ impl Lines {
     pub fn last_mut(&mut self) -> Option<&mut Line> {
         self.lines.last_mut()
     }
+    /// Prepends the given tokens to every line
+    pub fn prepend(&mut self, tokens: &TokenStream) -> &mut Self {
+        self.lines.iter_mut().for_each(|line| {
+            line.prepend(tokens);
+        });
+        self
+    }
+    /// Draws the carets of every line with the given glyph set
+    pub fn apply_glyphs(&mut self, glyphs: &GlyphSet) -> &mut Self {
+        self.lines.iter_mut().for_each(|line| {
+            line.apply_glyphs(glyphs);
+        });
+        self
+    }
+    /// Drops the styles of every line, including the ones of the label messages
+    pub fn strip_styles(&mut self) -> &mut Self {
+        self.lines.iter_mut().for_each(|line| {
+            line.strip_styles();
+        });
+        self
+    }
 }
 impl IntoIterator for Lines {
     type Item = Line;
@@ -153,6 +156,67 @@ impl Line {
             }
         }
     }
+    pub fn prepend(&mut self, tokens: &TokenStream) -> &mut Self {
+        match self {
+            Line::Sep(line) | Line::Underbar(line) | Line::LabelSeq(line) | Line::Label(line) => {
+                let mut prepended = tokens.clone();
+                prepended.extend(std::mem::replace(line, TokenStream::new()));
+                *line = prepended;
+            }
+        }
+        self
+    }
+    pub fn apply_glyphs(&mut self, glyphs: &GlyphSet) -> &mut Self {
+        match self {
+            Line::Sep(line) | Line::Underbar(line) | Line::LabelSeq(line) | Line::Label(line) => {
+                line.apply_glyphs(glyphs);
+            }
+        }
+        self
+    }
+    pub fn strip_styles(&mut self) -> &mut Self {
+        match self {
+            Line::Sep(line) | Line::Underbar(line) | Line::LabelSeq(line) | Line::Label(line) => {
+                line.strip_styles();
+            }
+        }
+        self
+    }
+}
+
+/// The gutter on the left of each row, displaying the line numbers of the source lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gutter {
+    /// The amount of digits of the widest line number displayed
+    width: usize,
+}
+impl Gutter {
+    /// Creates a gutter wide enough to fit the given (1-based) line number
+    pub fn new(widest_number: usize) -> Self {
+        Self {
+            width: widest_number.to_string().len(),
+        }
+    }
+    /// The amount of digits of the widest line number displayed
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    /// The gutter of a source line, displaying its (1-based) line number
+    pub fn numbered(&self, number: usize) -> TokenStream {
+        TokenStream::from_iter([
+            Token::Literal(format!("{number:>width$} ", width = self.width)),
+            Token::VCaret,
+            Token::Space(1),
+        ])
+    }
+    /// The gutter of every other row, just padding the rows so the carets line up with the source
+    pub fn blank(&self) -> TokenStream {
+        TokenStream::from_iter([
+            Token::Space(self.width.sat_add(1)),
+            Token::VCaret,
+            Token::Space(1),
+        ])
+    }
 }
 impl Display for Line {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -167,10 +231,21 @@ impl Display for Line {
 
 #[derive(Debug, Clone, derive_more::IntoIterator)]
 pub struct ReportCaret {
+    /// Index of the source line this caret annotates
+    line: usize,
+    /// Index of the source line the label starts on and the column it starts at,
+    /// if it spans multiple lines
+    ///
+    /// The caret then annotates the last line of the span
+    span: Option<(usize, usize)>,
     /// Start of underbar
     start: usize,
     /// End of underbar
     end: usize,
+    /// The byte range of the source the labels were given, printed by `ReportBuilder::with_range`
+    ///
+    /// Unlike start and end, it is neither rebased onto the line nor shifted by trimming or tabs
+    source_range: RangeInclusive,
     /// Relative to start
     /// Reversed, so that popping gets the leftmost position first
     #[into_iterator]
@@ -178,19 +253,25 @@ pub struct ReportCaret {
 }
 impl PartialEq for ReportCaret {
     fn eq(&self, other: &Self) -> bool {
-        self.start == other.start && self.end == other.end
+        self.line == other.line && self.start == other.start && self.end == other.end
     }
 }
 impl Eq for ReportCaret {}
 impl PartialOrd for ReportCaret {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.start.cmp(&other.start).then(self.end.cmp(&other.end)))
+        Some(
+            self.line
+                .cmp(&other.line)
+                .then(self.start.cmp(&other.start))
+                .then(self.end.cmp(&other.end)),
+        )
     }
 }
 impl Ord for ReportCaret {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.start
-            .cmp(&other.start)
+        self.line
+            .cmp(&other.line)
+            .then(self.start.cmp(&other.start))
             .then(self.end.cmp(&other.end))
             .then(self.rev_positions.len().cmp(&other.rev_positions.len()))
             .then_with(|| {
@@ -212,16 +293,35 @@ impl Ord for ReportCaret {
 }
 
 impl ReportCaret {
-    pub(super) fn new(start: usize, end: usize, mut rev_positions: Vec<ReportLabel>) -> Self {
+    pub(super) fn new(
+        line: usize,
+        start: usize,
+        end: usize,
+        mut rev_positions: Vec<ReportLabel>,
+    ) -> Self {
         rev_positions.sort_by(|a, b| a.cmp(b));
         rev_positions.dedup();
         rev_positions.reverse();
         Self {
+            line,
+            span: None,
             start,
             end,
+            source_range: (start..=end).into(),
             rev_positions,
         }
     }
+    /// Sets the byte range of the source the labels of the caret were given
+    pub(super) fn with_source_range(mut self, range: RangeInclusive) -> Self {
+        self.source_range = range;
+        self
+    }
+    /// Marks the caret as the end of a label spanning from the given source line
+    /// (starting at the given column) onto its own line
+    pub(super) fn spanning_from(mut self, first: usize, column: usize) -> Self {
+        self.span = Some((first, column));
+        self
+    }
     pub fn range(&self) -> RangeInclusive {
         (self.start..=self.end).into()
     }
@@ -351,12 +451,9 @@ impl ReportCaret {
         ranges
     }
 
-    pub(self) fn format(mut self) -> Option<Lines> {
-        // We wanna deref them once, so we dont need to do unsafe blocks everywhere else
-        #[allow(non_snake_case)]
-        let ARROR_LABEL_PADDING_REF = unsafe { ARROR_LABEL_PADDING };
-        #[allow(non_snake_case)]
-        let CHILD_LABEL_OFFSET_DEREF = unsafe { CHILD_LABEL_OFFSET };
+    pub(self) fn format(mut self, config: &RenderConfig) -> Option<Lines> {
+        let arrow_label_padding = config.arrow_label_padding;
+        let child_label_offset = config.child_label_offset;
 
         if self.is_empty() {
             return None;
@@ -560,7 +657,7 @@ impl ReportCaret {
                         } else {
                             Token::VCaret
                         },
-                        Token::Space(ARROR_LABEL_PADDING_REF),
+                        Token::Space(arrow_label_padding),
                     ]);
                     line.extend(first);
                     lines.push(Line::Label(line));
@@ -573,10 +670,8 @@ impl ReportCaret {
                         label_line.push(Token::Space(2));
                     }
                     // We want a space for multiline labels, to indicate that there are more lines
-                    label_line.push_iter([
-                        Token::VCaret,
-                        Token::Space(ARROR_LABEL_PADDING_REF.sat_add(1)),
-                    ]);
+                    label_line
+                        .push_iter([Token::VCaret, Token::Space(arrow_label_padding.sat_add(1))]);
 
                     for line in message {
                         let mut label_line = (&label_line).clone();
@@ -592,7 +687,7 @@ impl ReportCaret {
                             } else {
                                 Token::VCaret
                             },
-                            Token::Space(ARROR_LABEL_PADDING_REF),
+                            Token::Space(arrow_label_padding),
                         ]);
                         label_line.extend(line);
                         lines.push(Line::Label(label_line));
@@ -623,7 +718,7 @@ impl ReportCaret {
                         .into_inner();
 
                     // Add spaces until we reach the caret of the parent label +  2 (for the arrow-transition)
-                    let current_pos = (sep).lit_len();
+                    let current_pos = (sep).width();
                     if current_pos == 0 {
                         let target_pos = parent_label_position
                             // 2 for the arrow-transition
@@ -648,7 +743,7 @@ impl ReportCaret {
                             pcc(Token::HDown),
                             pcc(Token::HCaret(2)),
                             pcc(Token::VLeft),
-                            Token::Space(ARROR_LABEL_PADDING_REF),
+                            Token::Space(arrow_label_padding),
                         ]);
                         label_line.extend(line);
                         lines.push(Line::Label(label_line));
@@ -660,7 +755,7 @@ impl ReportCaret {
                                 Token::Space(2),
                                 pcc(Token::VCaret),
                                 // offset by 1 to indicate that the line was split
-                                Token::Space(ARROR_LABEL_PADDING_REF.sat_add(1)),
+                                Token::Space(arrow_label_padding.sat_add(1)),
                             ]);
                             label_line.extend(line);
                             lines.push(Line::LabelSeq(label_line));
@@ -674,7 +769,7 @@ impl ReportCaret {
                         pcc(Token::HDown),
                         pcc(Token::HCaret(2)),
                         pcc(Token::LArrow),
-                        Token::Space(ARROR_LABEL_PADDING_REF),
+                        Token::Space(arrow_label_padding),
                     ]);
 
                     label_line.extend(message.into_iter().next().unwrap());
@@ -691,7 +786,7 @@ impl ReportCaret {
                     .into_inner();
 
                 // Add spaces until we reach the caret of the parent label + CHILD_LABEL_PADDING (4) (for the arrow-transition)
-                let current_pos = (child_sep).lit_len();
+                let current_pos = (child_sep).width();
                 if current_pos == 0 {
                     let target_pos = parent_label_position
                         // 2 for the arrow-transition
@@ -747,36 +842,36 @@ impl ReportCaret {
                             (true, true, true) => {
                                 child_line.push_iter([
                                     ccc(Token::UpRight),
-                                    ccc(Token::HCaret(CHILD_LABEL_OFFSET_DEREF.sat_add(2))),
+                                    ccc(Token::HCaret(child_label_offset.sat_add(2))),
                                     ccc(Token::LArrow),
-                                    Token::Space(ARROR_LABEL_PADDING_REF),
+                                    Token::Space(arrow_label_padding),
                                 ]);
                             }
                             // Only line in label-child, but not the last child label
                             (true, false, true) => {
                                 child_line.push_iter([
                                     pcc(Token::VRight),
-                                    ccc(Token::HCaret(CHILD_LABEL_OFFSET_DEREF.sat_add(2))),
+                                    ccc(Token::HCaret(child_label_offset.sat_add(2))),
                                     ccc(Token::LArrow),
-                                    Token::Space(ARROR_LABEL_PADDING_REF),
+                                    Token::Space(arrow_label_padding),
                                 ]);
                             }
                             // First line but not last in label-child, not last child label
                             (true, true, false) => {
                                 child_line.push_iter([
                                     pcc(Token::UpRight),
-                                    ccc(Token::HCaret(CHILD_LABEL_OFFSET_DEREF.sat_add(2))),
+                                    ccc(Token::HCaret(child_label_offset.sat_add(2))),
                                     ccc(Token::VLeft),
-                                    Token::Space(ARROR_LABEL_PADDING_REF),
+                                    Token::Space(arrow_label_padding),
                                 ]);
                             }
                             // First line but not last in label-child, last child label
                             (true, false, false) => {
                                 child_line.push_iter([
                                     pcc(Token::VRight),
-                                    ccc(Token::HCaret(CHILD_LABEL_OFFSET_DEREF.sat_add(2))),
+                                    ccc(Token::HCaret(child_label_offset.sat_add(2))),
                                     ccc(Token::VLeft),
-                                    Token::Space(ARROR_LABEL_PADDING_REF),
+                                    Token::Space(arrow_label_padding),
                                 ]);
                             }
                             // not last Child label, but not the only line
@@ -784,20 +879,20 @@ impl ReportCaret {
                                 child_line.push_iter([
                                     // We do not wanna style the first caret, as it is the continuation of the parent label
                                     Token::VCaret,
-                                    Token::Space(CHILD_LABEL_OFFSET_DEREF.sat_add(2)),
+                                    Token::Space(child_label_offset.sat_add(2)),
                                     ccc(Token::VCaret),
                                     // offset by 1 to indicate that the line was split
-                                    Token::Space(ARROR_LABEL_PADDING_REF.sat_add(1)),
+                                    Token::Space(arrow_label_padding.sat_add(1)),
                                 ]);
                             }
                             // Last Child label, but not the only line
                             (_, true, false) => {
                                 child_line.push_iter([
                                     // We need to add an extra space here, as there are not more child labels, thus no carets which would offset the line
-                                    Token::Space(CHILD_LABEL_OFFSET_DEREF.sat_add(3)),
+                                    Token::Space(child_label_offset.sat_add(3)),
                                     ccc(Token::VCaret),
                                     // offset by 1 to indicate that the line was split
-                                    Token::Space(ARROR_LABEL_PADDING_REF.sat_add(1)),
+                                    Token::Space(arrow_label_padding.sat_add(1)),
                                 ]);
                             }
                             (_, _, true) => {
@@ -868,7 +963,7 @@ impl ReportCaret {
 }
 impl Display for ReportCaret {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(formatted) = self.clone().format() {
+        if let Some(formatted) = self.clone().format(&RenderConfig::default()) {
             write!(f, "{formatted:#}")
         } else {
             std::fmt::Result::Err(std::fmt::Error)
@@ -977,164 +1072,342 @@ impl ReportLabels {
     pub fn len(&self) -> usize {
         self.labels.len()
     }
-    pub fn write<T: std::io::Write, A: AsRef<[Token]>>(
+    /// Lays out the caret blocks of all labels, one token stream per row
+    pub fn format(
         &self,
-        mut writer: T,
-        ref_input: A,
+        lines: &[SourceLine],
         colored_input: bool,
         display_range: bool,
-    ) -> std::io::Result<()> {
-        if self.is_empty() {
-            return Ok(());
-        }
-
-        let ref_input: TokenBuffer = ref_input.as_ref().into();
-
+        gutter: Option<Gutter>,
+        config: &RenderConfig,
+    ) -> std::io::Result<Vec<TokenStream>> {
         let len = self.labels.len().saturating_sub(1);
-
-        self.labels.iter().enumerate().try_for_each(|(i, label)| {
-            Self::write_single(
-                &mut writer,
-                label,
-                colored_input,
-                display_range,
-                len == i,
-                &ref_input,
-            )
-        })
-    }
-    pub(self) fn write_single<'a, W: Write, I: Into<TokenBuffer<'a>>>(
+        let margin = Self::has_spans(&self.labels);
+
+        let mut rows = Vec::new();
+        self.labels
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, label)| -> std::io::Result<()> {
+                rows.extend(Self::format_single(
+                    label,
+                    colored_input,
+                    display_range,
+                    gutter,
+                    margin,
+                    len == i,
+                    lines,
+                    config,
+                )?);
+                Ok(())
+            })?;
+        Ok(rows)
+    }
+    /// A margin for the span connectors is only needed if any label spans multiple lines
+    pub(self) fn has_spans(labels: &[ReportCaret]) -> bool {
+        labels.iter().any(|label| label.span.is_some())
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub(self) fn write_single<W: Write>(
         writer: &mut W,
         label: &ReportCaret,
         colored_input: bool,
         display_range: bool,
+        gutter: Option<Gutter>,
+        margin: bool,
         is_last: bool,
-        ref_input: I,
+        lines: &[SourceLine],
+        config: &RenderConfig,
     ) -> std::io::Result<()> {
-        let range = label.range();
-        let ref_input: TokenBuffer = ref_input.into();
+        Self::format_single(
+            label,
+            colored_input,
+            display_range,
+            gutter,
+            margin,
+            is_last,
+            lines,
+            config,
+        )
+        .and_then(|rows| write!(writer, "{:#}", Rows(&rows)))
+    }
+    /// Lays out the caret block of a single label, one token stream per row
+    ///
+    /// Unless it is the last block, it ends with an empty row separating it from the next one.
+    #[allow(clippy::too_many_arguments)]
+    pub(self) fn format_single(
+        label: &ReportCaret,
+        colored_input: bool,
+        display_range: bool,
+        gutter: Option<Gutter>,
+        margin: bool,
+        is_last: bool,
+        lines: &[SourceLine],
+        config: &RenderConfig,
+    ) -> std::io::Result<Vec<TokenStream>> {
+        let range = label.source_range;
+        let line = &lines[label.line];
+        // Pre-colored source lines are just as plain as everything else in plain text mode
+        let source = |input: &TokenStream| {
+            let mut input = input.clone();
+            if config.plain {
+                input.strip_styles();
+            }
+            input
+        };
+        let input = source(&line.input);
+        let ref_input: TokenBuffer = (&input).into();
+        let glyphs = &config.glyphs;
+        // The source itself is never redrawn, only the gutter and carets around it
+        let draw = |mut tokens: TokenStream| {
+            tokens.apply_glyphs(glyphs);
+            tokens
+        };
 
-        if colored_input {
-            let color_ranges = label.get_underbar_ranges();
-            let mut index = 0;
+        // Starts each row of the source with its gutter (if any)
+        let numbered = |line: &SourceLine| {
+            gutter
+                .map(|gutter| draw(gutter.numbered(line.number())))
+                .unwrap_or_else(TokenStream::new)
+        };
 
-            if color_ranges.is_empty() {
-                write!(writer, "{:#}", ref_input)?;
+        // The connectors of a span are drawn just like the carets of its label
+        #[cfg(feature = "caret_color")]
+        let caret_color = label
+            .iter()
+            .next()
+            .and_then(|label| label.message.ref_color());
+        #[cfg(not(feature = "caret_color"))]
+        let caret_color: Option<&RgbColor> = None;
+        let connector = |token: Token| {
+            let mut token = if config.plain {
+                token
             } else {
-                let ref_input_str = ref_input.to_string();
+                token.try_with_coloring_feature(caret_color)
+            };
+            token.apply_glyphs(glyphs);
+            token
+        };
 
-                for (start, len, color) in color_ranges {
-                    if start > index {
-                        // Write plain until we reach the start
-                        if let Some(ref_input_slice) = ref_input_str.get(index..start) {
-                            write!(writer, "{:#}", ref_input_slice)?;
-                        }
-                        index = start;
+        let mut rows = Vec::new();
+        if let Some((first, column)) = label.span {
+            // Lead in to the line the span ends on, connecting all lines in between
+            lines[first..label.line]
+                .iter()
+                .enumerate()
+                .for_each(|(i, spanned)| {
+                    let mut row = numbered(spanned);
+                    let spanned_input = source(&spanned.input);
+                    if i > 0 {
+                        row.push(connector(Token::VCaret));
+                        row.push(Token::Space(1));
+                        row.extend(spanned_input);
+                        rows.push(row);
+                        return;
                     }
-                    if let Some(color) = color {
-                        if let Some(ref_input_slice) = ref_input_str.get(index..start.sat_add(len))
-                        {
-                            write!(
-                                writer,
-                                "{color}{:#}{}",
-                                ref_input_slice,
-                                AnsiStyle::RESET_COLOR
-                            )?;
-                        }
-                    } else {
-                        if let Some(ref_input_slice) = ref_input_str.get(index..start.sat_add(len))
-                        {
-                            write!(writer, "{:#}", ref_input_slice)?;
-                        }
+                    row.push(connector(Token::DownRight));
+                    row.push(Token::Space(1));
+                    // Underline the first line from where the span starts
+                    let width =
+                        width::display_width(&TokenBuffer::from(&spanned_input).to_string());
+                    row.extend(spanned_input);
+                    rows.push(row);
+                    let mut start = gutter
+                        .map(|gutter| draw(gutter.blank()))
+                        .unwrap_or_else(TokenStream::new);
+                    start.push(connector(Token::VCaret));
+                    start.push(Token::Space(column.sat_add(1)));
+                    start.push(connector(Token::HCaret(width.sat_sub(column).max(1))));
+                    rows.push(start);
+                });
+        }
+
+        let mut row = numbered(line);
+        if label.span.is_some() {
+            row.push(connector(Token::UpRight));
+            row.push(Token::Space(1));
+        } else if margin {
+            row.push(Token::Space(2));
+        }
+
+        let color_ranges = label.get_underbar_ranges();
+        if colored_input && !config.plain && !color_ranges.is_empty() {
+            let ref_input_str = ref_input.to_string();
+            let mut index = 0;
+
+            for (start, len, color) in color_ranges {
+                // The ranges are given in columns, so map them back onto the graphemes they cover
+                let end = width::offset_of(&ref_input_str, start.sat_add(len));
+                let start = width::offset_of(&ref_input_str, start);
+                if start > index {
+                    // Plain until we reach the start
+                    if let Some(ref_input_slice) = ref_input_str.get(index..start) {
+                        row.push(Token::Literal(ref_input_slice.into()));
                     }
-                    index = start.sat_add(len);
+                    index = start;
                 }
-
-                if index < ref_input_str.len() {
-                    if let Some(ref_input_slice) = ref_input_str.get(index..ref_input_str.len()) {
-                        write!(writer, "{:#}", ref_input_slice)?;
+                if let Some(ref_input_slice) = ref_input_str.get(index..end) {
+                    if let Some(color) = color {
+                        row.push(Token::Styled(AnsiStyle::RgbColor(color), None));
+                        row.push(Token::Literal(ref_input_slice.into()));
+                        row.push(Token::Styled(AnsiStyle::RESET_COLOR, None));
+                    } else {
+                        row.push(Token::Literal(ref_input_slice.into()));
                     }
                 }
+                index = end;
             }
-            if display_range {
-                writeln!(writer, " [{range:#}]")?;
-            } else {
-                writeln!(writer)?;
+
+            if let Some(ref_input_slice) = ref_input_str.get(index..)
+                && !ref_input_slice.is_empty()
+            {
+                row.push(Token::Literal(ref_input_slice.into()));
             }
         } else {
-            if display_range {
-                writeln!(writer, "{:#} [{range:#}]", ref_input)?;
-            } else {
-                writeln!(writer, "{:#}", ref_input)?;
-            }
+            row.extend(input.clone());
         }
-
-        writeln!(writer, "{:#}", label)?;
+        if display_range {
+            row.push(Token::Literal(format!(" [{range:#}]")));
+        }
+        rows.push(row);
+
+        // Pad the caret rows, so that they line up with the reference input
+        let mut padding = gutter
+            .map(|gutter| draw(gutter.blank()))
+            .unwrap_or_else(TokenStream::new);
+        if margin {
+            padding.push(Token::Space(2));
+        }
+        let Some(mut formatted) = label.clone().format(config) else {
+            // Fails just like formatting an empty caret would
+            return Err(std::io::Error::other(std::fmt::Error));
+        };
+        if !padding.is_empty() {
+            formatted.prepend(&padding);
+        }
+        // Only once the lines are complete, as prepending merges adjacent tokens
+        formatted.apply_glyphs(glyphs);
+        if config.plain {
+            formatted.strip_styles();
+        }
+        rows.extend(formatted.into_inner());
         // Just add a separator line between
-        if is_last { Ok(()) } else { writeln!(writer) }
+        if !is_last {
+            rows.push(TokenStream::new());
+        }
+        Ok(rows)
     }
+}
 
-    pub fn into_writer<'a, W: Write, A: AsRef<[Token]>>(
-        &'a self,
-        writer: &'a mut W,
-        reference_input: &'a A,
-        display_range: bool,
-    ) -> ReportWriter<'a, W> {
-        ReportWriter::new(
-            writer,
-            reference_input.as_ref(),
-            &self.labels,
-            display_range,
-        )
+/// Flattens the carets of all sections, so that they can be written one after another
+///
+/// Each caret is given by its section and its index within that section
+fn flatten_sections(sections: &[Section]) -> Vec<(&Section, usize)> {
+    sections
+        .iter()
+        .flat_map(|section| (0..section.report_labels.len()).map(move |index| (section, index)))
+        .collect()
+}
+
+/// The formatted lines of a header, footer or patch, dropping their styles in plain text mode
+pub(super) fn formatted_rows(
+    mut lines: Vec<TokenStream>,
+    config: &RenderConfig,
+) -> Vec<TokenStream> {
+    if config.plain {
+        lines.iter_mut().for_each(TokenStream::strip_styles);
     }
-    pub fn into_writer_with<
-        'a,
-        W: Write,
-        D: Display,
-        I: Display,
-        B: FnMut(ReportWriterMeta) -> Option<D>,
-        A: for<'b> FnMut(Option<&'b io::Error>, ReportWriterMeta) -> Option<I>,
-        T: AsRef<[Token]>,
-    >(
-        &'a self,
-        writer: &'a mut W,
-        reference_input: &'a T,
-        display_range: bool,
-        callback_before: B,
-        callback_after: A,
-    ) -> ReportWriterWith<'a, W, D, I, B, A> {
-        ReportWriterWith::new(
-            writer,
-            reference_input.as_ref(),
-            &self.labels,
-            display_range,
-            callback_before,
-            callback_after,
-        )
+    lines
+}
+
+/// Writes the formatted lines of a header, footer or patch, see `formatted_rows`
+fn write_formatted<W: Write>(
+    writer: &mut W,
+    lines: Vec<TokenStream>,
+    config: &RenderConfig,
+) -> std::io::Result<()> {
+    write!(writer, "{:#}", Rows(&formatted_rows(lines, config)))
+}
+
+/// Displays rows separated by newlines, the alternate form terminates the last row as well
+///
+/// Each row is displayed in its alternate form, so that its styles are reset at its end.
+pub(super) struct Rows<'a>(pub(super) &'a [TokenStream]);
+impl Display for Rows<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let terminated = f.alternate();
+        self.0.iter().enumerate().try_for_each(|(i, row)| {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{row:#}")
+        })?;
+        if terminated && !self.0.is_empty() {
+            writeln!(f)?;
+        }
+        Ok(())
     }
 }
 
+pub(super) fn write_header<W: Write>(
+    writer: &mut W,
+    header: &Header,
+    config: &RenderConfig,
+) -> std::io::Result<()> {
+    write_formatted(writer, header.lines(), config)
+}
+
+pub(super) fn write_footers<W: Write>(
+    writer: &mut W,
+    footers: &[Footer],
+    config: &RenderConfig,
+) -> std::io::Result<()> {
+    footers
+        .iter()
+        .try_for_each(|footer| write_formatted(writer, footer.format(), config))
+}
+
+/// Writes the patches of a section, each preceded by a separator line
+pub(super) fn write_patches<W: Write>(
+    writer: &mut W,
+    patches: &[Patch],
+    config: &RenderConfig,
+) -> std::io::Result<()> {
+    patches.iter().try_for_each(|patch| {
+        writeln!(writer)?;
+        write_formatted(writer, patch.format(), config)
+    })
+}
+
 #[derive(Debug)]
 pub struct ReportWriter<'a, W: Write> {
-    writer: &'a mut W,
-    reference_input: &'a [Token],
+    writer: DepthWriter<&'a mut W>,
     index: usize,
-    report_labels: &'a [ReportCaret],
+    /// The carets of all sections, see `flatten_sections`
+    carets: Vec<(&'a Section, usize)>,
+    /// Written after the last caret block
+    footers: &'a [Footer],
     display_range: bool,
+    gutter: Option<Gutter>,
+    config: RenderConfig,
 }
 impl<'a, W: Write> ReportWriter<'a, W> {
     pub(crate) fn new(
         writer: &'a mut W,
-        reference_input: &'a [Token],
-        report_labels: &'a [ReportCaret],
+        sections: &'a [Section],
+        footers: &'a [Footer],
         display_range: bool,
+        gutter: Option<Gutter>,
+        config: RenderConfig,
     ) -> Self {
         Self {
-            writer,
-            reference_input,
+            writer: DepthWriter::new(writer, config.color_depth),
             index: 0,
-            report_labels,
+            carets: flatten_sections(sections),
+            footers,
             display_range,
+            gutter,
+            config,
         }
     }
     pub fn write(mut self) -> std::io::Result<()> {
@@ -1145,23 +1418,56 @@ impl<'a, W: Write> ReportWriter<'a, W> {
 impl<W: Write> Iterator for ReportWriter<'_, W> {
     type Item = std::io::Result<()>;
     fn next(&mut self) -> Option<Self::Item> {
-        let len = self.report_labels.len();
+        let len = self.carets.len();
         if self.index >= len {
             return None;
         }
-        let label: &ReportCaret = &self.report_labels[self.index];
+        let (section, caret) = self.carets[self.index];
+        let label: &ReportCaret = &section.report_labels.labels[caret];
+
+        // Each section starts with its header
+        if caret == 0
+            && let Some(header) = &section.header
+            && let Err(e) = write_header(&mut self.writer, header, &self.config)
+        {
+            return Some(Err(e));
+        }
 
         let is_last = len == self.index.sat_add(1);
         self.index += 1;
+        // The patches follow the last caret block of their section
+        let patches = if caret.sat_add(1) == section.report_labels.len() {
+            section.patches.as_slice()
+        } else {
+            &[]
+        };
 
-        Some(ReportLabels::write_single(
-            self.writer,
+        let res = ReportLabels::write_single(
+            &mut self.writer,
             label,
             true,
             self.display_range,
-            is_last,
-            self.reference_input,
-        ))
+            self.gutter,
+            ReportLabels::has_spans(&section.report_labels.labels),
+            is_last || !patches.is_empty(),
+            &section.lines,
+            &self.config,
+        )
+        .and_then(|_| {
+            if patches.is_empty() {
+                return Ok(());
+            }
+            write_patches(&mut self.writer, patches, &self.config)?;
+            if is_last {
+                Ok(())
+            } else {
+                writeln!(self.writer)
+            }
+        });
+        if res.is_ok() && is_last {
+            return Some(write_footers(&mut self.writer, self.footers, &self.config));
+        }
+        Some(res)
     }
 }
 
@@ -1204,11 +1510,15 @@ pub struct ReportWriterWith<
     B: FnMut(ReportWriterMeta) -> Option<D>,
     A: for<'b> FnMut(Option<&'b io::Error>, ReportWriterMeta) -> Option<I>,
 > {
-    writer: &'a mut W,
-    reference_input: &'a [Token],
+    writer: DepthWriter<&'a mut W>,
     index: usize,
-    report_labels: &'a [ReportCaret],
+    /// The carets of all sections, see `flatten_sections`
+    carets: Vec<(&'a Section, usize)>,
+    /// Written after the last caret block
+    footers: &'a [Footer],
     display_range: bool,
+    gutter: Option<Gutter>,
+    config: RenderConfig,
 
     callback_before: B,
     callback_after: A,
@@ -1224,20 +1534,25 @@ impl<
     A: for<'b> FnMut(Option<&'b io::Error>, ReportWriterMeta) -> Option<I>,
 > ReportWriterWith<'a, W, D, I, B, A>
 {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         writer: &'a mut W,
-        reference_input: &'a [Token],
-        report_labels: &'a [ReportCaret],
+        sections: &'a [Section],
+        footers: &'a [Footer],
         display_range: bool,
+        gutter: Option<Gutter>,
+        config: RenderConfig,
         callback_before: B,
         callback_after: A,
     ) -> Self {
         Self {
-            writer,
-            reference_input,
+            writer: DepthWriter::new(writer, config.color_depth),
             index: 0,
-            report_labels,
+            carets: flatten_sections(sections),
+            footers,
             display_range,
+            gutter,
+            config,
             callback_after,
             callback_before,
             _marker: std::marker::PhantomData,
@@ -1260,7 +1575,7 @@ impl<
     type Item = std::io::Result<()>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let len = self.report_labels.len();
+        let len = self.carets.len();
         if self.index >= len {
             return None;
         }
@@ -1272,7 +1587,16 @@ impl<
             is_only: len == 1,
         };
 
-        let label: &ReportCaret = &self.report_labels[self.index];
+        let (section, caret) = self.carets[self.index];
+        let label: &ReportCaret = &section.report_labels.labels[caret];
+
+        // The header of each section always precedes the users callback
+        if caret == 0
+            && let Some(header) = &section.header
+            && let Err(e) = write_header(&mut self.writer, header, &self.config)
+        {
+            return Some(Err(e));
+        }
 
         self.index += 1;
 
@@ -1286,15 +1610,42 @@ impl<
             needs_sep = true;
         }
 
+        // The patches follow the last caret block of their section
+        let patches = if caret.sat_add(1) == section.report_labels.len() {
+            section.patches.as_slice()
+        } else {
+            &[]
+        };
+
         let res = ReportLabels::write_single(
-            self.writer,
+            &mut self.writer,
             label,
             true,
             self.display_range,
+            self.gutter,
+            ReportLabels::has_spans(&section.report_labels.labels),
             // We wanna force no trailing new line here, as that could mess up the users callback
-            !needs_sep,
-            self.reference_input,
-        );
+            !needs_sep || !patches.is_empty(),
+            &section.lines,
+            &self.config,
+        )
+        .and_then(|_| {
+            if patches.is_empty() {
+                return Ok(());
+            }
+            write_patches(&mut self.writer, patches, &self.config)?;
+            if needs_sep {
+                writeln!(self.writer)
+            } else {
+                Ok(())
+            }
+        });
+        // The footers belong to the report, so they precede the users callback
+        let res = if res.is_ok() && meta.is_last {
+            write_footers(&mut self.writer, self.footers, &self.config)
+        } else {
+            res
+        };
 
         if let Some(display) = (self.callback_after)(res.as_ref().err(), meta) {
             self.writer
@@ -1305,3 +1656,148 @@ impl<
         Some(res)
     }
 }
+
+#[test]
+fn multi_line_source_with_range() {
+    use crate::{Label, ReportBuilder, Trim};
+
+    let input = "[package]\nname = \"demo\"\nversion = 1.0";
+    let mut report = ReportBuilder::new(input)
+        .trim_input(Trim::None)
+        .plain_text(true)
+        .with_range();
+    report.push(Label::new(17..=22, "quoted"));
+    report.push(Label::new(34..=36, "not a string"));
+    // Only the annotated lines are printed, the ranges being the byte offsets into the whole input
+    let expected = [
+        "name = \"demo\" [17 - 22]",
+        "       ──┬──",
+        "         │  ",
+        "         ╰─▶ quoted",
+        "",
+        "version = 1.0 [34 - 36]",
+        "          ┬─",
+        "          │ ",
+        "          ╰─▶ not a string",
+        "",
+    ];
+    assert_eq!(report.finish().unwrap().to_string(), expected.join("\n"));
+
+    // Trimming shifts the printed line, but not the printed range
+    let mut report = ReportBuilder::new("one two three four five six\nseven")
+        .plain_text(true)
+        .with_range();
+    report.push(Label::new(14..=17, "trimmed"));
+    let output = report.finish().unwrap().to_string();
+    assert!(
+        output.starts_with("... three four five ... [14 - 17]\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn gutter_fits_widest_line_number() {
+    use crate::{ChildLabel, Label, ReportBuilder, Trim};
+
+    let input = (1..=12)
+        .map(|i| format!("line {i}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut report = ReportBuilder::new(input.as_str())
+        .trim_input(Trim::None)
+        .plain_text(true)
+        .with_line_numbers();
+    report.push(Label::new(56..=59, "ninth"));
+    let expected = ["9 │ line 9", "  │ ─┬─", "  │  │ ", "  │  ╰─▶ ninth", ""];
+    assert_eq!(report.finish().unwrap().to_string(), expected.join("\n"));
+
+    // Line 10 widens the gutter of every row, the carets still lining up with the source
+    report
+        .push(Label::new(63..=66, "tenth\nsecond line").with_child_label(ChildLabel::new("child")));
+    let expected = [
+        " 9 │ line 9",
+        "   │ ─┬─",
+        "   │  │ ",
+        "   │  ╰─▶ ninth",
+        "",
+        "10 │ line 10",
+        "   │ ─┬─",
+        "   │  │ ",
+        "   │  ╰──┬──┤ tenth",
+        "   │     │  │  second line",
+        "   │     │",
+        "   │     ╰─────▶ child",
+        "",
+    ];
+    assert_eq!(report.finish().unwrap().to_string(), expected.join("\n"));
+}
+
+#[test]
+fn span_connectors() {
+    use crate::{GlyphSet, Label, ReportBuilder, Trim};
+
+    let input = "fn main() {\n    let x = 1;\n}";
+    let mut report = ReportBuilder::new(input)
+        .trim_input(Trim::None)
+        .plain_text(true);
+    report.push(Label::new(10..=27, "body"));
+    // The first line is underlined from where the span starts
+    let expected = [
+        "╭ fn main() {",
+        "│           ─",
+        "│     let x = 1;",
+        "╰ }",
+        "  ┬",
+        "  │",
+        "  ╰─▶ body",
+        "",
+    ];
+    assert_eq!(report.finish().unwrap().to_string(), expected.join("\n"));
+
+    // Every caret block prints the lines it annotates,
+    // thus the first line of the span is printed again for its own label
+    report.push(Label::new(3..=6, "name"));
+    let report = report.with_line_numbers();
+    let expected = [
+        "1 │   fn main() {",
+        "  │      ─┬─",
+        "  │       │ ",
+        "  │       ╰─▶ name",
+        "",
+        "1 │ ╭ fn main() {",
+        "  │ │           ─",
+        "2 │ │     let x = 1;",
+        "3 │ ╰ }",
+        "  │   ┬",
+        "  │   │",
+        "  │   ╰─▶ body",
+        "",
+    ];
+    assert_eq!(report.finish().unwrap().to_string(), expected.join("\n"));
+
+    // The connectors are drawn with the glyphs of the report, unlike box-drawing characters of the source
+    let mut report = ReportBuilder::new("{ │\n}")
+        .trim_input(Trim::None)
+        .plain_text(true)
+        .with_glyphs(GlyphSet::ASCII);
+    report.push(Label::new(0..=6, "block"));
+    let output = report.finish().unwrap().to_string();
+    assert!(output.starts_with(", { │\n| ---\n` }\n"), "{output}");
+
+    // ... and colored just like its carets
+    #[cfg(feature = "caret_color")]
+    {
+        let mut report = ReportBuilder::new(input)
+            .trim_input(Trim::None)
+            .caret_color(true);
+        report.push(Label::new(10..=27, "body").with_color(AnsiStyle::RED));
+        let output = report.finish().unwrap().to_string();
+        let color = AnsiStyle::RgbColor(RgbColor::try_from(AnsiStyle::RED).unwrap());
+        ["╭", "│", "─", "╰"].iter().for_each(|glyph| {
+            assert!(
+                output.contains(&format!("{color}{glyph}")),
+                "{glyph} in {output:?}"
+            );
+        });
+    }
+}
@@ -0,0 +1,188 @@
+use ::std::sync::atomic::{AtomicUsize, Ordering};
+use ::token::{ColorDepth, GlyphSet};
+
+/// Defaults of `RenderConfig`, only changed through the compatibility setters in `crate::config`
+static DEFAULT_ARROW_LABEL_PADDING: AtomicUsize = AtomicUsize::new(1);
+static DEFAULT_CHILD_LABEL_OFFSET: AtomicUsize = AtomicUsize::new(3);
+
+/// Sets the arrow label padding of every `RenderConfig` created afterwards
+pub(crate) fn set_default_arrow_label_padding(padding: usize) {
+    DEFAULT_ARROW_LABEL_PADDING.store(padding, Ordering::Relaxed);
+}
+/// Sets the child label offset of every `RenderConfig` created afterwards
+pub(crate) fn set_default_child_label_offset(offset: usize) {
+    DEFAULT_CHILD_LABEL_OFFSET.store(offset, Ordering::Relaxed);
+}
+
+/// Layout settings used to render a report
+///
+/// Every report carries its own config, so reports rendered at the same time never affect each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderConfig {
+    /// The characters the carets, arrows and gutter are drawn with
+    pub(crate) glyphs: GlyphSet,
+    /// The colors are converted to this depth while writing, `ColorDepth::None` strips them
+    pub(crate) color_depth: ColorDepth,
+    /// Drops all styles, including the ones parsed from pre-colored label messages and input
+    pub(crate) plain: bool,
+    /// The amount of white-space padding to add directly after the arrow and before the label message
+    pub(crate) arrow_label_padding: usize,
+    /// The offset from the parent label to the child labels
+    pub(crate) child_label_offset: usize,
+    /// How much shorter child labels are wrapped than their parent labels,
+    /// to make up for their indentation
+    pub(crate) child_label_padding: usize,
+    /// The length at which label messages are wrapped
+    pub(crate) max_label_length: usize,
+    /// If not set, it will be set to "max_label_length - child_label_padding"
+    pub(crate) max_child_label_length: Option<usize>,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            glyphs: GlyphSet::default(),
+            color_depth: ColorDepth::default(),
+            plain: false,
+            arrow_label_padding: DEFAULT_ARROW_LABEL_PADDING.load(Ordering::Relaxed),
+            child_label_offset: DEFAULT_CHILD_LABEL_OFFSET.load(Ordering::Relaxed),
+            child_label_padding: 4,
+            // Default max label length is 30 characters
+            max_label_length: 30,
+            max_child_label_length: None,
+        }
+    }
+}
+
+impl RenderConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn glyphs(mut self, glyphs: GlyphSet) -> Self {
+        self.glyphs = glyphs;
+        self
+    }
+
+    pub fn color_depth(mut self, depth: ColorDepth) -> Self {
+        self.color_depth = depth;
+        self
+    }
+
+    pub fn plain(mut self, plain: bool) -> Self {
+        self.plain = plain;
+        self
+    }
+
+    pub fn arrow_label_padding(mut self, padding: usize) -> Self {
+        self.arrow_label_padding = padding;
+        self
+    }
+
+    pub fn child_label_offset(mut self, offset: usize) -> Self {
+        self.child_label_offset = offset;
+        self
+    }
+
+    pub fn child_label_padding(mut self, padding: usize) -> Self {
+        self.child_label_padding = padding;
+        self
+    }
+
+    pub fn max_label_length(mut self, length: usize) -> Self {
+        self.max_label_length = length;
+        self
+    }
+
+    pub fn max_child_label_length(mut self, length: usize) -> Self {
+        self.max_child_label_length = Some(length);
+        self
+    }
+
+    /// The length at which child label messages are wrapped
+    pub(crate) fn child_label_length(&self) -> usize {
+        self.max_child_label_length.unwrap_or(
+            self.max_label_length
+                .saturating_sub(self.child_label_padding),
+        )
+    }
+}
+
+#[test]
+fn render_config_per_report() {
+    use crate::{Label, ReportBuilder, Trim};
+
+    let render = |config: RenderConfig| {
+        let mut report = ReportBuilder::new("let x = 1;")
+            .trim_input(Trim::None)
+            .with_render_config(config);
+        report.push(Label::new(4..=5, "label"));
+        let mut output = Vec::new();
+        report.finish().unwrap().write(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    let narrow = render(RenderConfig::new());
+    let wide = render(RenderConfig::new().arrow_label_padding(3));
+    assert!(narrow.contains("─▶ label"));
+    assert!(wide.contains("─▶   label"));
+}
+
+#[test]
+fn ascii_glyphs() {
+    use crate::{Label, ReportBuilder, Trim};
+    use ::token::GlyphSet;
+
+    let mut report = ReportBuilder::new("let x = 1;\nlet y = x;")
+        .trim_input(Trim::None)
+        .with_line_numbers()
+        .with_glyphs(GlyphSet::ASCII);
+    report.push(Label::new(4..=5, "label"));
+    report.push(Label::new(8..=14, "span"));
+    let mut output = Vec::new();
+    report.finish().unwrap().write(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("`-> label"), "{output}");
+    assert!(output.contains("-+-"), "{output}");
+    assert!(output.is_ascii(), "{output}");
+}
+
+#[test]
+fn plain_text_strips_styles() {
+    use crate::{AnsiStyle, Label, ReportBuilder, Severity, Trim};
+
+    let render = |input: &str, suggest: bool| {
+        let mut report = ReportBuilder::new(input)
+            .trim_input(Trim::None)
+            .with_severity(Severity::Warning)
+            .with_title("unused variable")
+            .with_note(format!(
+                "{}pre-colored{}",
+                AnsiStyle::GREEN,
+                AnsiStyle::RESET
+            ))
+            .colored_input(true)
+            .plain_text(true);
+        let label = Label::new(
+            4..=5,
+            format!("{}styled{}", AnsiStyle::BLUE, AnsiStyle::RESET),
+        )
+        .with_color(AnsiStyle::RED);
+        report.push(if suggest {
+            label.with_suggestion(4..=4, "_x")
+        } else {
+            label
+        });
+        let mut output = Vec::new();
+        report.finish().unwrap().write(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    let output = render("let x = 1;", true);
+    assert!(!output.contains('\u{1b}'), "{output:?}");
+    assert!(output.contains("warning: unused variable"));
+    assert!(output.contains("styled"));
+    assert!(output.contains("+ let _x = 1;"));
+    assert!(output.contains("= note: pre-colored"));
+    // Pre-colored input
+    let output = render("let \u{1b}[31mx\u{1b}[0m = 1;", false);
+    assert!(!output.contains('\u{1b}'), "{output:?}");
+}
@@ -0,0 +1,113 @@
+use ::std::io::{self, Write};
+
+use ::token::ColorDepth;
+
+/// Converts the escape sequences written through it to the given color depth
+///
+/// Escape sequences (and characters) split across two writes are held back until they are complete,
+/// so they are always converted as a whole.
+#[derive(Debug)]
+pub(crate) struct DepthWriter<W: Write> {
+    inner: W,
+    depth: ColorDepth,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> DepthWriter<W> {
+    pub(crate) fn new(inner: W, depth: ColorDepth) -> Self {
+        Self {
+            inner,
+            depth,
+            pending: Vec::new(),
+        }
+    }
+
+    /// The length of the pending bytes that can be converted right away
+    fn complete_len(&self) -> usize {
+        let pending = &self.pending;
+        // An escape sequence that still lacks its final byte
+        let complete = match pending.iter().rposition(|&b| b == 0x1b) {
+            Some(esc)
+                if pending
+                    .get(esc.saturating_add(1))
+                    .is_none_or(|&b| b == b'[')
+                    && pending[esc..]
+                        .iter()
+                        .skip(2)
+                        .all(|b| b.is_ascii_digit() || *b == b';') =>
+            {
+                esc
+            }
+            _ => pending.len(),
+        };
+        // A character that still lacks some of its bytes
+        match std::str::from_utf8(&pending[..complete]) {
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            _ => complete,
+        }
+    }
+
+    fn write_converted(&mut self, len: usize) -> io::Result<()> {
+        let bytes = self.pending.drain(..len).collect::<Vec<_>>();
+        match std::str::from_utf8(&bytes) {
+            Ok(text) => self.inner.write_all(self.depth.convert(text).as_bytes()),
+            // Not text, so there is nothing to convert either
+            Err(_) => self.inner.write_all(&bytes),
+        }
+    }
+}
+
+impl<W: Write> Write for DepthWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.depth == ColorDepth::TrueColor {
+            return self.inner.write(buf);
+        }
+        self.pending.extend_from_slice(buf);
+        let len = self.complete_len();
+        self.write_converted(len)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let len = self.pending.len();
+        self.write_converted(len)?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for DepthWriter<W> {
+    fn drop(&mut self) {
+        // Whatever is left is incomplete, thus written as is
+        let _ = self.inner.write_all(&self.pending);
+    }
+}
+
+#[test]
+fn depth_writer_downgrades_colors() {
+    use crate::{Label, Palette, ReportBuilder, Trim};
+
+    let render = |depth: ColorDepth| {
+        let mut report = ReportBuilder::new("let x = 1;")
+            .trim_input(Trim::None)
+            .with_palette(Palette::default())
+            .with_color_depth(depth);
+        report.push(Label::new(4..=5, "label"));
+        let mut output = Vec::new();
+        report.finish().unwrap().write(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    assert!(render(ColorDepth::TrueColor).contains("\u{1b}[38;2;"));
+    let ansi256 = render(ColorDepth::Ansi256);
+    assert!(ansi256.contains("\u{1b}[38;5;") && !ansi256.contains("\u{1b}[38;2;"));
+    assert!(!render(ColorDepth::None).contains('\u{1b}'));
+
+    // Sequences split across writes are still converted as a whole
+    let mut output = Vec::new();
+    {
+        let mut writer = DepthWriter::new(&mut output, ColorDepth::Ansi16);
+        writer.write_all(b"\x1b[38;2;197;").unwrap();
+        writer.write_all(b"15;12mred\x1b[0m").unwrap();
+        writer.flush().unwrap();
+    }
+    assert_eq!(output, b"\x1b[31mred\x1b[0m");
+}
@@ -0,0 +1,105 @@
+use ::token::{saturating::SaturatingArithmetic, width};
+
+use super::*;
+
+/// A free-standing message printed once after all caret blocks
+///
+/// Formatted as `= note: message`, with wrapped lines aligned to the start of the message
+#[derive(Debug, Clone)]
+pub struct Footer {
+    /// Usually either `Severity::Note` or `Severity::Help`
+    pub(super) severity: Severity,
+    pub(super) message: LineTokenStream,
+    /// Indentation of the footer, so that it lines up with the gutter
+    pub(super) indent: usize,
+}
+impl Footer {
+    pub fn new(severity: Severity, message: LineTokenStream) -> Self {
+        Self {
+            severity,
+            message,
+            indent: 0,
+        }
+    }
+
+    pub fn with_gutter(mut self, gutter: Option<Gutter>) -> Self {
+        // Puts the "=" right below the separator of the gutter
+        self.indent = gutter.map_or(0, |gutter| gutter.width().sat_add(1));
+        self
+    }
+
+    pub fn format(&self) -> Vec<TokenStream> {
+        let name = self.severity.name();
+        // "= " + name + ": "
+        let continuation = self.indent.sat_add(width::display_width(name)).sat_add(4);
+        self.message
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                let mut stream = TokenStream::new();
+                if i == 0 {
+                    stream.push(Token::Space(self.indent));
+                    stream.push(Token::Literal("= ".into()));
+                    stream.push(Token::Styled(
+                        self.severity.style(),
+                        Some(Box::new(Token::Styled(
+                            AnsiStyle::BOLD,
+                            Some(Box::new(Token::Literal(name.into()))),
+                        ))),
+                    ));
+                    stream.push(Token::Literal(": ".into()));
+                } else {
+                    stream.push(Token::Space(continuation));
+                }
+                stream.extend(line.clone());
+                stream
+            })
+            .collect()
+    }
+}
+
+impl Display for Footer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Rows(&self.format()))
+    }
+}
+
+#[test]
+fn footers_after_carets() {
+    use crate::{Label, ReportBuilder, Trim, summary::strip_styles};
+
+    let mut report = ReportBuilder::new("let x = 1;")
+        .trim_input(Trim::None)
+        .with_line_numbers()
+        .with_note("variables are immutable by default")
+        .with_help("consider making it mutable: `let mut x = 1;`, or assigning another variable")
+        .max_footer_length(40);
+    report.push(Label::new(4..=4, "x"));
+    // The "=" lines up with the gutter, wrapped lines with the start of the message
+    let expected = [
+        "1 │ let x = 1;",
+        "  │     ┬",
+        "  │     │",
+        "  │     ╰─▶ x",
+        "  = note: variables are immutable by default",
+        "  = help: consider making it mutable: `let mut x",
+        "          = 1;`, or assigning another variable",
+        "",
+    ];
+    let plain = report.clone().plain_text(true).finish().unwrap();
+    assert_eq!(plain.to_string(), expected.join("\n"));
+
+    let footer = Footer::new(Severity::Help, LineTokenStream::from("try")).to_string();
+    let style = format!("{}{}", AnsiStyle::BRIGHT_CYAN, AnsiStyle::BOLD);
+    assert!(footer.starts_with(&format!("= {style}help")), "{footer:?}");
+    assert_eq!(strip_styles(&footer), "= help: try");
+
+    // Wrapped lines line up with the message, no matter how many bytes the name takes
+    let hinweis = Severity::custom("hinweis→", AnsiStyle::BRIGHT_CYAN);
+    let message = "first\nsecond".parse().unwrap();
+    let footer = Footer::new(hinweis, message).to_string();
+    assert_eq!(
+        strip_styles(&footer),
+        "= hinweis→: first\n            second"
+    );
+}
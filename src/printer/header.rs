@@ -0,0 +1,162 @@
+use super::*;
+use crate::Position;
+
+/// The header lines printed before the caret blocks
+///
+/// The first line is formatted as `severity[code]: title`, where both the code and the title are optional.
+/// If a source name is known, it is followed by a `--> name:line:column` line.
+#[derive(Debug, Clone)]
+pub struct Header {
+    /// Only set if any of the severity, code or title were given
+    pub(super) severity: Option<Severity>,
+    pub(super) code: Option<String>,
+    pub(super) title: Option<String>,
+    pub(super) location: Option<Location>,
+    /// Indentation of the location line, so that it lines up with the gutter
+    pub(super) indent: usize,
+}
+impl Header {
+    /// Returns None if there is nothing to print
+    pub fn new(
+        severity: Option<Severity>,
+        code: Option<String>,
+        title: Option<String>,
+        location: Option<Location>,
+    ) -> Option<Self> {
+        // The severity defaults to Error, as soon as any part of the first line is set
+        let severity = if code.is_some() || title.is_some() {
+            Some(severity.unwrap_or_default())
+        } else {
+            severity
+        };
+        (severity.is_some() || location.is_some()).then_some(Self {
+            severity,
+            code,
+            title,
+            location,
+            indent: 0,
+        })
+    }
+
+    pub fn with_gutter(mut self, gutter: Option<Gutter>) -> Self {
+        self.indent = gutter.map_or(0, |gutter| gutter.width());
+        self
+    }
+
+    pub fn format(&self) -> Option<TokenStream> {
+        let severity = self.severity.as_ref()?;
+        let mut stream = TokenStream::new();
+        let prefix = match &self.code {
+            Some(code) => format!("{severity}[{code}]"),
+            None => severity.to_string(),
+        };
+        stream.push(Token::Styled(
+            severity.style(),
+            Some(Box::new(Token::Styled(
+                AnsiStyle::BOLD,
+                Some(Box::new(Token::Literal(prefix))),
+            ))),
+        ));
+        if let Some(title) = &self.title {
+            stream.push(Token::Literal(": ".into()));
+            stream.push(Token::Styled(
+                AnsiStyle::BOLD,
+                Some(Box::new(Token::Literal(title.clone()))),
+            ));
+        }
+        Some(stream)
+    }
+
+    pub fn format_location(&self) -> Option<TokenStream> {
+        let location = self.location.as_ref()?;
+        Some(TokenStream::from_iter([
+            Token::Space(self.indent),
+            Token::Literal(format!("--> {location}")),
+        ]))
+    }
+
+    /// The severity line followed by the location line, if they are set
+    pub fn lines(&self) -> Vec<TokenStream> {
+        [self.format(), self.format_location()]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+impl Display for Header {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Rows(&self.lines()))
+    }
+}
+
+/// The position a report points to, printed in its header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// The name of the source, e.g. a path or `<stdin>`
+    pub(super) name: String,
+    /// The 1-based line
+    pub(super) line: usize,
+    /// The 1-based column, counted in characters
+    pub(super) column: usize,
+}
+impl Location {
+    /// Takes the position as given in the summary, its column counting characters rather than bytes
+    pub fn new<I: Into<String>>(name: I, position: Position) -> Self {
+        Self {
+            name: name.into(),
+            line: position.line,
+            column: position.column,
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.name, self.line, self.column)
+    }
+}
+
+#[test]
+fn header_line() {
+    use crate::{Label, ReportBuilder, summary::strip_styles};
+
+    // Nothing to print without any of the parts
+    assert!(Header::new(None, None, None, None).is_none());
+    // The severity defaults to Error once a code or title is given
+    let header = Header::new(None, Some("E0042".into()), Some("title".into()), None)
+        .unwrap()
+        .to_string();
+    let bold = AnsiStyle::BOLD;
+    assert!(header.starts_with(&format!("{}{bold}error[E0042]", AnsiStyle::BRIGHT_RED)));
+    assert_eq!(strip_styles(&header), "error[E0042]: title");
+    let lint = Severity::custom("lint", AnsiStyle::BRIGHT_BLUE);
+    let header = Header::new(Some(lint), None, None, None)
+        .unwrap()
+        .to_string();
+    assert!(header.starts_with(&format!("{}{bold}lint", AnsiStyle::BRIGHT_BLUE)));
+    assert_eq!(strip_styles(&header), "lint");
+
+    let mut report = ReportBuilder::new("let x = 1;")
+        .with_severity(Severity::Warning)
+        .with_code("W0001")
+        .plain_text(true);
+    report.push(Label::new(4..=4, "unused"));
+    let output = report.finish().unwrap().to_string();
+    assert!(output.starts_with("warning[W0001]\nlet x"), "{output}");
+}
+
+#[test]
+fn location_counts_characters() {
+    use crate::{Label, ReportBuilder};
+
+    let mut report = ReportBuilder::new("let x = 1;\nlet ä = 🦀;")
+        .with_source_name("main.rs")
+        .plain_text(true);
+    report.push(Label::new(20..=23, "crab"));
+    let report = report.finish().unwrap();
+    let output = report.to_string();
+    // "ä" takes two bytes, but only a single column
+    assert_eq!(output.lines().next(), Some("--> main.rs:2:9"), "{output}");
+    assert_eq!(report.summary().labels[0].start.column, 9);
+}
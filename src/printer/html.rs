@@ -0,0 +1,109 @@
+use super::{
+    runs::{RunStyle, hex, styled_rows},
+    *,
+};
+
+impl Report {
+    /// Writes the report as an HTML `<pre>` block, turning its styles into `<span>`s with inline styles
+    ///
+    /// The source and messages are escaped, so the block can be embedded into any page as is.
+    /// The carets only line up if the block is rendered in a monospace font (the default of `<pre>`).
+    /// Colors are always written in full, no matter the color depth of the report.
+    pub fn write_html<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let lines = styled_rows(&self.rows()?);
+        let mut html = String::from("<pre class=\"report\">");
+        lines.iter().for_each(|line| {
+            line.iter().for_each(|run| {
+                if run.style == RunStyle::default() {
+                    escape_html(&run.text, &mut html);
+                } else {
+                    html.push_str(&format!("<span style=\"{}\">", run.style.css()));
+                    escape_html(&run.text, &mut html);
+                    html.push_str("</span>");
+                }
+            });
+            html.push('\n');
+        });
+        html.push_str("</pre>\n");
+        writer.write_all(html.as_bytes())
+    }
+}
+
+impl RunStyle {
+    fn css(&self) -> String {
+        let mut css = Vec::new();
+        if let Some(color) = &self.color {
+            css.push(format!("color:{}", hex(color)));
+        }
+        if self.bold {
+            css.push("font-weight:bold".into());
+        }
+        if self.dim {
+            css.push("opacity:0.7".into());
+        }
+        if self.italic {
+            css.push("font-style:italic".into());
+        }
+        match (self.underline, self.strikethrough) {
+            (true, true) => css.push("text-decoration:underline line-through".into()),
+            (true, false) => css.push("text-decoration:underline".into()),
+            (false, true) => css.push("text-decoration:line-through".into()),
+            (false, false) => {}
+        }
+        if self.hidden {
+            css.push("visibility:hidden".into());
+        }
+        css.join(";")
+    }
+}
+
+/// Escapes the text for HTML (and XML) documents
+pub(super) fn escape_html(text: &str, html: &mut String) {
+    text.chars().for_each(|c| match c {
+        '&' => html.push_str("&amp;"),
+        '<' => html.push_str("&lt;"),
+        '>' => html.push_str("&gt;"),
+        '"' => html.push_str("&quot;"),
+        '\'' => html.push_str("&#39;"),
+        c => html.push(c),
+    });
+}
+
+#[test]
+fn html_escapes_and_styles() {
+    use crate::{Label, ReportBuilder, Trim};
+
+    let mut report = ReportBuilder::new("<a href=\"x\">link</a>").trim_input(Trim::None);
+    report.push(Label::new(1..=1, "tag & <attribute>").with_color(AnsiStyle::RED));
+    let mut html = Vec::new();
+    report.finish().unwrap().write_html(&mut html).unwrap();
+    let html = String::from_utf8(html).unwrap();
+    assert!(html.starts_with("<pre class=\"report\">&lt;a href=&quot;x&quot;&gt;link&lt;/a&gt;\n"));
+    assert!(html.contains("<span style=\"color:#c50f0c\">tag &amp; &lt;attribute&gt;</span>"));
+    assert!(!html.contains('\u{1b}'));
+    // The carets keep their columns, as only the styles are replaced
+    let plain = html
+        .split('\n')
+        .map(|line| {
+            let mut plain = String::new();
+            let mut in_tag = false;
+            line.chars().for_each(|c| match c {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                c if !in_tag => plain.push(c),
+                _ => {}
+            });
+            plain
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(plain[1].find('┬'), Some(1));
+
+    // Nested styles are combined, e.g. the struck through removals of a patch
+    let mut report = ReportBuilder::new("let x = 1;").trim_input(Trim::None);
+    report.push(Label::new(4..=4, "x").with_suggestion(4..=4, "y"));
+    let mut html = Vec::new();
+    report.finish().unwrap().write_html(&mut html).unwrap();
+    let html = String::from_utf8(html).unwrap();
+    let removed = "<span style=\"color:#c50f0c;text-decoration:line-through\">x</span>";
+    assert!(html.contains(removed), "{html}");
+}
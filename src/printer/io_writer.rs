@@ -0,0 +1,54 @@
+use ::std::{
+    fmt,
+    io::{self, Write},
+};
+
+/// Forwards the text written through it to an `io::Write` target
+///
+/// Allows the `fmt::Write` based rendering (`Report::write_into`) to write into files, sockets, ...
+/// `fmt::Error` can not carry an error, so the one of the target is kept until it is taken.
+#[derive(Debug)]
+pub(crate) struct IoWriter<'a, W: Write> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<'a, W: Write> IoWriter<'a, W> {
+    pub(crate) fn new(inner: &'a mut W) -> Self {
+        Self { inner, error: None }
+    }
+
+    /// The error of the target, if writing failed because of it
+    pub(crate) fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+impl<W: Write> fmt::Write for IoWriter<'_, W> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.inner.write_all(text.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+#[test]
+fn io_writer_keeps_the_error() {
+    use crate::{Label, ReportBuilder};
+
+    struct Full;
+    impl Write for Full {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::StorageFull.into())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut report = ReportBuilder::new("let x = 1;");
+    report.push(Label::new(4..=4, "never read"));
+    let error = report.finish().unwrap().write(&mut Full).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+}
@@ -0,0 +1,54 @@
+use super::*;
+
+/// The serialized form of a report, its summary along with the rendered text
+#[derive(serde::Serialize)]
+struct JsonReport<'a> {
+    #[serde(flatten)]
+    summary: &'a ReportSummary,
+    /// Rendered without any colors
+    rendered: String,
+}
+
+impl Report {
+    /// Writes the report as a single JSON object
+    ///
+    /// Contains the severity, code and title, every label with its byte range, positions,
+    /// message and child labels, the footers and finally the rendered report without colors.
+    pub fn write_json<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut rendered = String::new();
+        self.render(&mut rendered, ColorDepth::None)?;
+        let json = JsonReport {
+            summary: &self.summary,
+            rendered,
+        };
+        serde_json::to_writer(writer, &json).map_err(io::Error::from)
+    }
+}
+
+#[test]
+fn json_contains_ranges_and_rendered_text() {
+    use crate::{Label, ReportBuilder, Trim};
+
+    let mut report = ReportBuilder::new("let x = 1;")
+        .with_severity(Severity::Warning)
+        .with_code("W001")
+        .with_note("variables are immutable by default")
+        .trim_input(Trim::None);
+    report.push(Label::new(4..=4, "unused").with_color(AnsiStyle::RED));
+    let mut json = Vec::new();
+    report.finish().unwrap().write_json(&mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["severity"], "warning");
+    assert_eq!(json["code"], "W001");
+    let label = &json["labels"][0];
+    assert_eq!(label["range"], serde_json::json!({ "start": 4, "end": 5 }));
+    assert_eq!(
+        label["start"],
+        serde_json::json!({ "line": 1, "column": 5, "utf16_column": 5 })
+    );
+    assert_eq!(label["message"], "unused");
+    assert_eq!(json["footers"][0]["severity"], "note");
+    let rendered = json["rendered"].as_str().unwrap();
+    assert!(rendered.starts_with("warning[W001]"));
+    assert!(rendered.contains("let x = 1;") && !rendered.contains('\u{1b}'));
+}
@@ -0,0 +1,130 @@
+use ::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString, Uri,
+};
+
+use super::*;
+use crate::{LabelSummary, Position};
+
+impl Report {
+    /// Converts the report into an LSP diagnostic, located at its first label
+    ///
+    /// The remaining labels and all child labels become related information.
+    /// As related information may point into other documents,
+    /// `uri` maps the name of a source (None for unnamed sources) to its document.
+    pub fn to_lsp_diagnostic<F: Fn(Option<&str>) -> Uri>(&self, uri: F) -> Diagnostic {
+        let summary = &self.summary;
+        // Labels are required by the builder, so there always is a first one
+        let (primary, secondary) = summary
+            .labels
+            .split_first()
+            .expect("A report has at least one label");
+        // Editors show nothing but the message, so the title must not hide the label
+        let mut message = match &summary.title {
+            Some(title) => format!("{title}\n{}", primary.message),
+            None => primary.message.clone(),
+        };
+        summary.footers.iter().for_each(|footer| {
+            message.push_str(&format!("\n{}: {}", footer.severity, footer.message));
+        });
+
+        let related = |label: &LabelSummary, message: &str| DiagnosticRelatedInformation {
+            location: Location::new(uri(label.source.as_deref()), label.lsp_range()),
+            message: message.to_string(),
+        };
+        let related_information = primary
+            .children
+            .iter()
+            .map(|child| related(primary, child))
+            .chain(secondary.iter().flat_map(|label| {
+                std::iter::once(related(label, &label.message)).chain(
+                    label
+                        .children
+                        .iter()
+                        .map(move |child| related(label, child)),
+                )
+            }))
+            .collect::<Vec<_>>();
+
+        Diagnostic {
+            range: primary.lsp_range(),
+            severity: Some(lsp_severity(&summary.severity)),
+            code: summary.code.clone().map(NumberOrString::String),
+            message,
+            related_information: (!related_information.is_empty()).then_some(related_information),
+            ..Diagnostic::default()
+        }
+    }
+}
+
+impl LabelSummary {
+    /// The range of the label, in UTF-16 code units
+    pub fn lsp_range(&self) -> lsp_types::Range {
+        lsp_types::Range::new(self.start.into(), self.end.into())
+    }
+}
+
+impl From<Position> for lsp_types::Position {
+    fn from(position: Position) -> Self {
+        // LSP positions are 0-based
+        let zero_based = |n: usize| u32::try_from(n.saturating_sub(1)).unwrap_or(u32::MAX);
+        Self::new(zero_based(position.line), zero_based(position.utf16_column))
+    }
+}
+
+/// Custom severities are treated as informational
+fn lsp_severity(severity: &Severity) -> DiagnosticSeverity {
+    match severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+        Severity::Help => DiagnosticSeverity::HINT,
+        Severity::Note | Severity::Custom { .. } => DiagnosticSeverity::INFORMATION,
+    }
+}
+
+#[test]
+fn lsp_diagnostic_counts_utf16_units() {
+    use ::std::str::FromStr;
+
+    use crate::{ChildLabel, Label, ReportBuilder, Sources};
+
+    let mut sources = Sources::new();
+    sources.add("main.rs", "let 🦀 = ferris;\nlet x = 🦀;");
+    let lib = sources.add("lib.rs", "pub fn ferris() {}");
+    let mut report = ReportBuilder::from_sources(sources)
+        .with_severity(Severity::Warning)
+        .with_code("W002")
+        .with_title("shadowed crab");
+    report.push(
+        Label::new(19..=31, "used here").with_child_label(ChildLabel::new(
+            "the crab is four bytes, but two UTF-16 units",
+        )),
+    );
+    report.push(Label::new(7..=12, "defined here").in_file(lib));
+    let report = report.finish().unwrap();
+    let diagnostic = report.to_lsp_diagnostic(|name| {
+        Uri::from_str(&format!("file:///src/{}", name.unwrap_or("unknown"))).unwrap()
+    });
+    assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(diagnostic.code, Some(NumberOrString::String("W002".into())));
+    assert_eq!(diagnostic.message, "shadowed crab\nused here");
+    // "let x = 🦀;" spans the whole second line, the crab counting twice
+    assert_eq!(
+        diagnostic.range,
+        lsp_types::Range::new(
+            lsp_types::Position::new(1, 0),
+            lsp_types::Position::new(1, 11)
+        )
+    );
+    let related = diagnostic.related_information.unwrap();
+    assert_eq!(related.len(), 2);
+    assert_eq!(
+        related[0].message,
+        "the crab is four bytes, but two UTF-16 units"
+    );
+    assert_eq!(related[1].message, "defined here");
+    assert_eq!(related[1].location.uri.as_str(), "file:///src/lib.rs");
+    assert_eq!(
+        related[1].location.range.start,
+        lsp_types::Position::new(0, 7)
+    );
+}
@@ -1,28 +1,119 @@
 use ::std::{
-    fmt::Display,
+    fmt::{self, Display},
     io::{self, Write},
 };
 
-use ::token::*;
+use ::token::{saturating::SaturatingArithmetic, *};
 
-use crate::{Find, FindRev, builder::*};
+use crate::{Find, FindRev, ReportSummary, Severity, builder::*};
 
 mod builder;
 mod carets;
+mod config;
+mod depth;
+mod footer;
+mod header;
+mod html;
+mod io_writer;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod patch;
+mod runs;
+#[cfg(feature = "serde")]
+mod sarif;
+mod svg;
 mod underbar;
 pub(super) use builder::*;
 pub(crate) use carets::*;
-pub use carets::{set_arrow_label_padding, set_child_label_offset};
+pub use config::RenderConfig;
+pub(crate) use config::{set_default_arrow_label_padding, set_default_child_label_offset};
+pub(crate) use depth::*;
+pub(crate) use footer::*;
+pub(crate) use header::*;
+pub(crate) use io_writer::*;
+pub(crate) use patch::*;
+#[cfg(feature = "serde")]
+pub use sarif::SarifLog;
 
 impl Report {
-    pub fn write<W: Write>(self, writer: &mut W) -> io::Result<()> {
-        self.report_labels
-            .write(writer, &self.input, self.colored_input, self.display_range)
+    /// Renders the report into the writer, it can be rendered any number of times
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut target = IoWriter::new(writer);
+        self.render(&mut target, self.config.color_depth)
+            .map_err(|error| target.take_error().unwrap_or(error))?;
+        writer.flush()
+    }
+
+    /// Renders the report into a `fmt::Write` target, e.g. a `String` or a `Formatter`
+    pub fn write_into<F: fmt::Write>(&self, writer: &mut F) -> fmt::Result {
+        self.render(writer, self.config.color_depth)
+            .map_err(|_| fmt::Error)
+    }
+
+    /// Renders the rows as text, converting their colors to the given depth
+    pub(super) fn render<F: fmt::Write>(
+        &self,
+        writer: &mut F,
+        depth: ColorDepth,
+    ) -> io::Result<()> {
+        let rows = Rows(&self.rows()?);
+        if depth == ColorDepth::TrueColor {
+            write!(writer, "{rows:#}")
+        } else {
+            writer.write_str(&depth.convert(&format!("{rows:#}")))
+        }
+        .map_err(io::Error::other)
+    }
+
+    /// Lays out the whole report, one token stream per row
+    ///
+    /// Every backend renders these rows, so that their output always matches the terminal layout.
+    pub(super) fn rows(&self) -> io::Result<Vec<TokenStream>> {
+        let config = &self.config;
+        let mut rows = Vec::new();
+        let len = self.sections.len();
+        self.sections
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, section)| -> io::Result<()> {
+                if let Some(header) = &section.header {
+                    rows.extend(formatted_rows(header.lines(), config));
+                }
+                rows.extend(section.report_labels.format(
+                    &section.lines,
+                    self.colored_input,
+                    self.display_range,
+                    self.gutter,
+                    config,
+                )?);
+                section.patches.iter().for_each(|patch| {
+                    // Each patch is preceded by a separator line
+                    rows.push(TokenStream::new());
+                    rows.extend(formatted_rows(patch.format(), config));
+                });
+                // Separate the sections just like the caret blocks
+                if i.sat_add(1) < len {
+                    rows.push(TokenStream::new());
+                }
+                Ok(())
+            })?;
+        self.footers
+            .iter()
+            .for_each(|footer| rows.extend(formatted_rows(footer.format(), config)));
+        Ok(rows)
     }
 
     pub fn into_writer<'a, W: Write>(&'a self, writer: &'a mut W) -> ReportWriter<'a, W> {
-        self.report_labels
-            .into_writer(writer, &self.input, self.display_range)
+        ReportWriter::new(
+            writer,
+            &self.sections,
+            &self.footers,
+            self.display_range,
+            self.gutter,
+            self.config,
+        )
     }
     pub fn into_writer_with<
         'a,
@@ -37,12 +128,40 @@ impl Report {
         callback_before: B,
         callback_after: A,
     ) -> ReportWriterWith<'a, W, D, I, B, A> {
-        self.report_labels.into_writer_with(
+        ReportWriterWith::new(
             writer,
-            &self.input,
+            &self.sections,
+            &self.footers,
             self.display_range,
+            self.gutter,
+            self.config,
             callback_before,
             callback_after,
         )
     }
 }
+
+/// Renders the report just like `Report::write`, e.g. to use it with `format!`
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_into(f)
+    }
+}
+
+#[test]
+fn render_by_reference() {
+    use crate::{Label, ReportBuilder};
+
+    let mut report = ReportBuilder::new("let x = 1;");
+    report.push(Label::new(4..=4, "never read"));
+    let report = report.finish().unwrap();
+    let mut first = Vec::new();
+    report.write(&mut first).unwrap();
+    let mut second = Vec::new();
+    report.write(&mut second).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(report.to_string().as_bytes(), first);
+    let mut string = String::new();
+    report.write_into(&mut string).unwrap();
+    assert_eq!(string.as_bytes(), first);
+}
@@ -0,0 +1,302 @@
+use ::token::saturating::SaturatingArithmetic;
+
+use super::*;
+use crate::source::{ExpandedLine, SourceLines};
+
+/// A preview of source lines with suggestions applied
+///
+/// Printed after the caret blocks of its section as a "help: try this" block,
+/// listing the original lines (prefixed by `-`) followed by the patched lines (prefixed by `+`)
+#[derive(Debug, Clone)]
+pub struct Patch {
+    pub(super) title: Footer,
+    /// Only contains the original lines if the suggestions remove anything
+    pub(super) removed: Vec<(usize, TokenStream)>,
+    pub(super) added: Vec<(usize, TokenStream)>,
+    pub(super) gutter: Option<Gutter>,
+}
+impl Patch {
+    /// Groups the suggestions by the lines they touch and generates a patch per group
+    ///
+    /// The suggestions must be within the bounds of the source, on character boundaries
+    /// and must not overlap each other, all of which is validated by `ReportBuilder::finish`.
+    /// Tabs are expanded to the given width, so that the preview lines up with the caret blocks.
+    pub fn generate<'a>(
+        source: &SourceLines,
+        suggestions: impl IntoIterator<Item = &'a Suggestion>,
+        tab_width: usize,
+    ) -> Vec<Self> {
+        let mut suggestions = suggestions.into_iter().collect::<Vec<_>>();
+        suggestions.sort_by_key(|suggestion| (suggestion.range.start, suggestion.range.end));
+
+        // (first line, last line, suggestions)
+        let mut groups: Vec<(usize, usize, Vec<&Suggestion>)> = Vec::new();
+        suggestions.into_iter().for_each(|suggestion| {
+            let range = &suggestion.range;
+            let first = source.line_of(range.start);
+            let last = source.line_of(range.end.sat_sub(1).max(range.start));
+            match groups.last_mut() {
+                Some((_, group_last, group)) if first <= *group_last => {
+                    *group_last = (*group_last).max(last);
+                    group.push(suggestion);
+                }
+                _ => groups.push((first, last, vec![suggestion])),
+            }
+        });
+
+        groups
+            .into_iter()
+            .map(|(first, last, group)| Self::new(source, first, last, group, tab_width))
+            .collect()
+    }
+
+    fn new(
+        source: &SourceLines,
+        first: usize,
+        last: usize,
+        suggestions: Vec<&Suggestion>,
+        tab_width: usize,
+    ) -> Self {
+        let input = source.source();
+        let start = source.line_start(first);
+        let end = source.line_range(last).end;
+
+        let mut removed = PatchLines::new(first, tab_width);
+        let mut added = PatchLines::new(first, tab_width);
+        let mut removes_any = false;
+        let mut index = start;
+        suggestions.into_iter().for_each(|suggestion| {
+            let range = &suggestion.range;
+            let unchanged = &input[index..range.start];
+            removed.push(unchanged, Change::Unchanged);
+            added.push(unchanged, Change::Unchanged);
+            removes_any |= !range.is_empty();
+            removed.push(&input[range.clone()], Change::Removed);
+            added.push(&suggestion.replacement, Change::Added);
+            index = range.end;
+        });
+        let unchanged = &input[index.min(end)..end];
+        removed.push(unchanged, Change::Unchanged);
+        added.push(unchanged, Change::Unchanged);
+
+        Self {
+            title: Footer::new(Severity::Help, LineTokenStream::from("try this")),
+            removed: if removes_any {
+                removed.into_lines()
+            } else {
+                Vec::new()
+            },
+            added: added.into_lines(),
+            gutter: None,
+        }
+    }
+
+    pub fn with_gutter(mut self, gutter: Option<Gutter>) -> Self {
+        self.title = self.title.with_gutter(gutter);
+        self.gutter = gutter;
+        self
+    }
+
+    pub fn format(&self) -> Vec<TokenStream> {
+        let marked = |marker: Token, style: AnsiStyle, (number, line): &(usize, TokenStream)| {
+            let mut stream = TokenStream::new();
+            if let Some(gutter) = self.gutter {
+                stream.push(Token::Literal(format!(
+                    "{:>width$} ",
+                    number,
+                    width = gutter.width()
+                )));
+            }
+            stream.push(Token::Styled(style, Some(Box::new(marker))));
+            stream.push(Token::Space(1));
+            stream.extend(line.clone());
+            stream
+        };
+        self.title
+            .format()
+            .into_iter()
+            .chain(
+                self.removed
+                    .iter()
+                    .map(|line| marked(Token::Literal("-".into()), AnsiStyle::RED, line)),
+            )
+            .chain(
+                self.added
+                    .iter()
+                    .map(|line| marked(Token::Literal("+".into()), AnsiStyle::GREEN, line)),
+            )
+            .collect()
+    }
+}
+
+impl Display for Patch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Rows(&self.format()))
+    }
+}
+
+/// How a part of a patched line differs from the original
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    Unchanged,
+    Removed,
+    Added,
+}
+
+/// Collects (partly styled) text into numbered lines
+struct PatchLines {
+    /// The (0-based) index of the current line within the source
+    line: usize,
+    lines: Vec<(usize, TokenStream)>,
+    /// The parts of the current line, only styled once the line is complete,
+    /// as its tabs are expanded by the columns of the whole line
+    current: Vec<(String, Change)>,
+    tab_width: usize,
+}
+impl PatchLines {
+    fn new(line: usize, tab_width: usize) -> Self {
+        Self {
+            line,
+            lines: Vec::new(),
+            current: Vec::new(),
+            tab_width,
+        }
+    }
+
+    fn push(&mut self, text: &str, change: Change) {
+        let mut parts = text.split('\n');
+        if let Some(part) = parts.next() {
+            self.push_part(part, change);
+        }
+        parts.for_each(|part| {
+            self.break_line();
+            self.push_part(part, change);
+        });
+    }
+
+    fn push_part(&mut self, part: &str, change: Change) {
+        let part = part.trim_end_matches('\r');
+        if part.is_empty() {
+            return;
+        }
+        self.current.push((part.to_string(), change));
+    }
+
+    fn break_line(&mut self) {
+        let parts = std::mem::take(&mut self.current);
+        let text = parts
+            .iter()
+            .map(|(part, _)| part.as_str())
+            .collect::<String>();
+        let expanded = ExpandedLine::new(&text, self.tab_width);
+        let mut start = 0;
+        let current = parts
+            .into_iter()
+            .map(|(part, change)| {
+                let end = start.sat_add(part.len());
+                let part = &expanded.text()[expanded.offset(start)..expanded.offset(end)];
+                start = end;
+                let token = Token::Literal(part.to_string());
+                match change {
+                    Change::Unchanged => token,
+                    // Deletions are struck through, so they are recognizable without colors as well
+                    Change::Removed => Token::Styled(
+                        AnsiStyle::RED,
+                        Some(Box::new(Token::Styled(
+                            AnsiStyle::STRIKETHROUGH,
+                            Some(Box::new(token)),
+                        ))),
+                    ),
+                    Change::Added => Token::Styled(AnsiStyle::GREEN, Some(Box::new(token))),
+                }
+            })
+            .collect();
+        self.lines.push((self.line.sat_add(1), current));
+        self.line = self.line.sat_add(1);
+    }
+
+    fn into_lines(mut self) -> Vec<(usize, TokenStream)> {
+        self.break_line();
+        self.lines
+    }
+}
+
+#[test]
+fn patches_preview_suggestions() {
+    use crate::{Error, Label, ReportBuilder, Trim};
+
+    let input = "fn main() {\n    let x = 1;\n    x = 2;\n}";
+    let mut report = ReportBuilder::new(input)
+        .trim_input(Trim::None)
+        .with_line_numbers();
+    report.push(Label::new(20..=20, "immutable").with_insertion(20, "mut "));
+    report.push(Label::new(31..=36, "assigned twice").with_suggestion(31..=31, "y"));
+    let styled = report.finish().unwrap();
+    // Insertions only print the patched line
+    let expected = [
+        "",
+        "  = help: try this",
+        "2 +     let mut x = 1;",
+        "",
+        "  = help: try this",
+        "3 -     x = 2;",
+        "3 +     y = 2;",
+        "",
+    ];
+    let plain = report.plain_text(true).finish().unwrap().to_string();
+    assert!(plain.ends_with(&expected.join("\n")), "{plain}");
+    // Removals are struck through, so they are recognizable without colors as well
+    assert!(
+        styled
+            .to_string()
+            .contains(&AnsiStyle::STRIKETHROUGH.to_string())
+    );
+    let suggestions = styled
+        .suggestions()
+        .map(|suggestion| (suggestion.range(), suggestion.replacement()))
+        .collect::<Vec<_>>();
+    assert_eq!(suggestions, [(20..20, "mut "), (31..32, "y")]);
+
+    // An empty range inserts just like `with_insertion`
+    let mut report = ReportBuilder::new(input).plain_text(true);
+    report.push(Label::new(20..=20, "immutable").with_suggestion(20..20, "mut "));
+    let report = report.finish().unwrap();
+    assert_eq!(report.suggestions().next().unwrap().range(), 20..20);
+    let output = report.to_string();
+    assert!(output.ends_with("+     let mut x = 1;\n"), "{output}");
+
+    // Tabs are expanded just like in the caret blocks, so that both line up
+    let mut report = ReportBuilder::new("fn main() {\n\tlet x = 1;\n}")
+        .trim_input(Trim::None)
+        .plain_text(true);
+    report.push(Label::new(17..=17, "immutable").with_insertion(17, "mut "));
+    let output = report.finish().unwrap().to_string();
+    assert!(output.starts_with("    let x = 1;\n"), "{output}");
+    assert!(output.ends_with("+     let mut x = 1;\n"), "{output}");
+
+    // Suggestions touching the same lines share a single patch
+    let mut report = ReportBuilder::new(input).plain_text(true);
+    report.push(
+        Label::new(20..=20, "x")
+            .with_suggestion(24..=31, "1;\n    y")
+            .with_suggestion(35..=35, "3"),
+    );
+    let expected = [
+        "",
+        "= help: try this",
+        "-     let x = 1;",
+        "-     x = 2;",
+        "+     let x = 1;",
+        "+     y = 3;",
+        "",
+    ];
+    let output = report.finish().unwrap().to_string();
+    assert!(output.ends_with(&expected.join("\n")), "{output}");
+
+    // Overlapping suggestions could not be applied together
+    report.push(Label::new(31..=36, "assigned twice").with_suggestion(31..=36, "y = 3;"));
+    assert_eq!(
+        report.finish().err(),
+        Some(Error::OverlappingSuggestions(24..32, 31..37))
+    );
+}
@@ -0,0 +1,122 @@
+use super::*;
+
+/// The styles in effect at some point of a row of the report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct RunStyle {
+    pub color: Option<RgbColor>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub hidden: bool,
+}
+impl RunStyle {
+    /// Applies the style of a `Token::Styled`
+    fn apply(&mut self, style: &AnsiStyle) {
+        match style {
+            AnsiStyle::Color(color) => self.color = Some(RgbColor::from(*color)),
+            AnsiStyle::RgbColor(color) => self.color = Some(*color),
+            AnsiStyle::Style(style) => match style {
+                Style::Bold => self.bold = true,
+                Style::Dim => self.dim = true,
+                Style::Italic => self.italic = true,
+                Style::Underline => self.underline = true,
+                Style::Hidden => self.hidden = true,
+                Style::Strikethrough => self.strikethrough = true,
+                // Blinking and inverting have no reasonable equivalent outside of a terminal
+                Style::Blink | Style::Inverse => {}
+            },
+            AnsiStyle::Reset(reset) => match reset {
+                Resets::All => *self = Self::default(),
+                Resets::Style => {
+                    *self = Self {
+                        color: self.color,
+                        ..Self::default()
+                    }
+                }
+                Resets::Color | Resets::FgColor => self.color = None,
+                // Tokens never set a background color
+                Resets::BgColor => {}
+                Resets::Bold | Resets::Dim => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                Resets::Italic => self.italic = false,
+                Resets::Underline => self.underline = false,
+                Resets::Hidden => self.hidden = false,
+                Resets::Strikethrough => self.strikethrough = false,
+                Resets::Blink | Resets::Inverse => {}
+            },
+        }
+    }
+}
+
+/// The color in the `#rrggbb` notation of CSS and SVG
+pub(super) fn hex(color: &RgbColor) -> String {
+    let (r, g, b) = **color;
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// A piece of text sharing the same styles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Run {
+    pub style: RunStyle,
+    pub text: String,
+}
+
+/// Splits the rows of a report (see `Report::rows`) into styled runs
+///
+/// A styled token with content only styles its content, whereas one without content
+/// styles all tokens following it within the row, until they are reset.
+pub(super) fn styled_rows(rows: &[TokenStream]) -> Vec<Vec<Run>> {
+    rows.iter()
+        .map(|row| {
+            let mut runs = Vec::new();
+            let mut style = RunStyle::default();
+            row.iter()
+                .for_each(|token| push_token(&mut runs, &mut style, token));
+            runs
+        })
+        .collect()
+}
+
+fn push_token(runs: &mut Vec<Run>, style: &mut RunStyle, token: &Token) {
+    match token {
+        Token::Styled(ansi, Some(inner)) => {
+            let outer = *style;
+            style.apply(ansi);
+            push_token(runs, style, inner);
+            *style = outer;
+        }
+        Token::Styled(ansi, None) => style.apply(ansi),
+        Token::Reset => *style = RunStyle::default(),
+        Token::Literal(text) if text.contains('\u{1b}') => {
+            // Slices of pre-colored input are kept as text, so their escape sequences are tokenized here
+            let tokens = TokenStream::from(text);
+            if tokens.as_ref() == std::slice::from_ref(token) {
+                // Nothing the tokenizer recognizes
+                push_run(runs, *style, text);
+            } else {
+                tokens
+                    .iter()
+                    .for_each(|token| push_token(runs, style, token));
+            }
+        }
+        token => push_run(runs, *style, &token.to_string()),
+    }
+}
+
+/// Appends the text to the last run if it shares its style, otherwise starts a new run
+fn push_run(runs: &mut Vec<Run>, style: RunStyle, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match runs.last_mut() {
+        Some(run) if run.style == style => run.text.push_str(text),
+        _ => runs.push(Run {
+            style,
+            text: text.to_string(),
+        }),
+    }
+}
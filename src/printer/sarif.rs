@@ -0,0 +1,218 @@
+use ::serde_json::{Map, Value, json};
+
+use super::*;
+use crate::{LabelSummary, Position};
+
+/// A SARIF 2.1.0 log of a batch of reports, e.g. to feed code scanning UIs
+///
+/// Every report becomes a result of a single run. Labels become its locations,
+/// while child labels become related locations sharing the region of their label.
+#[derive(Debug, Clone)]
+pub struct SarifLog {
+    /// The name of the tool that produced the reports
+    tool: String,
+    tool_version: Option<String>,
+    reports: Vec<ReportSummary>,
+}
+
+impl SarifLog {
+    pub fn new<I: Display>(tool: I) -> Self {
+        Self {
+            tool: tool.to_string(),
+            tool_version: None,
+            reports: Vec::new(),
+        }
+    }
+
+    pub fn with_tool_version<I: Display>(mut self, version: I) -> Self {
+        self.tool_version = Some(version.to_string());
+        self
+    }
+
+    pub fn push(&mut self, report: &Report) -> &mut Self {
+        self.reports.push(report.summary().clone());
+        self
+    }
+
+    pub fn push_iter<'a, Iter: IntoIterator<Item = &'a Report>>(
+        &mut self,
+        reports: Iter,
+    ) -> &mut Self {
+        reports.into_iter().for_each(|report| {
+            self.push(report);
+        });
+        self
+    }
+
+    /// Writes the log as a single JSON document
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        serde_json::to_writer(writer, &self.to_value()).map_err(io::Error::from)
+    }
+
+    fn to_value(&self) -> Value {
+        let mut driver = Map::new();
+        driver.insert("name".into(), self.tool.clone().into());
+        if let Some(version) = &self.tool_version {
+            driver.insert("version".into(), version.clone().into());
+        }
+        let mut codes = self
+            .reports
+            .iter()
+            .filter_map(|report| report.code.clone())
+            .collect::<Vec<_>>();
+        codes.sort();
+        codes.dedup();
+        if !codes.is_empty() {
+            let rules = codes.into_iter().map(|code| json!({ "id": code }));
+            driver.insert("rules".into(), rules.collect());
+        }
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": { "driver": driver },
+                // The positions of the summaries count characters, not UTF-16 code units
+                "columnKind": "unicodeCodePoints",
+                "results": self.reports.iter().map(result).collect::<Vec<_>>(),
+            }],
+        })
+    }
+}
+
+/// The SARIF level of a severity, anything but errors and warnings being informational
+fn level(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        _ => "note",
+    }
+}
+
+fn result(report: &ReportSummary) -> Value {
+    // The title is optional, so the first label has to stand in for it
+    let mut text = report
+        .title
+        .clone()
+        .or_else(|| report.labels.first().map(|label| label.message.clone()))
+        .unwrap_or_default();
+    report.footers.iter().for_each(|footer| {
+        text.push_str(&format!("\n{}: {}", footer.severity, footer.message));
+    });
+
+    let mut id = 0;
+    let mut related = Vec::new();
+    let locations = report
+        .labels
+        .iter()
+        .map(|label| {
+            label.children.iter().for_each(|child| {
+                related.push(json!({
+                    "id": id,
+                    "physicalLocation": physical_location(label),
+                    "message": { "text": child },
+                }));
+                id = id.sat_add(1);
+            });
+            json!({
+                "physicalLocation": physical_location(label),
+                "message": { "text": label.message },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut result = Map::new();
+    if let Some(code) = &report.code {
+        result.insert("ruleId".into(), code.clone().into());
+    }
+    result.insert("level".into(), level(&report.severity).into());
+    result.insert("message".into(), json!({ "text": text }));
+    result.insert("locations".into(), locations.into());
+    if !related.is_empty() {
+        result.insert("relatedLocations".into(), related.into());
+    }
+    result.into()
+}
+
+/// The region of the label, within its source if the source was given a name
+fn physical_location(label: &LabelSummary) -> Value {
+    let Position {
+        line: start_line,
+        column: start_column,
+        ..
+    } = label.start;
+    let Position {
+        line: end_line,
+        column: end_column,
+        ..
+    } = label.end;
+    let mut location = Map::new();
+    if let Some(source) = &label.source {
+        location.insert("artifactLocation".into(), json!({ "uri": source }));
+    }
+    location.insert(
+        "region".into(),
+        json!({
+            "startLine": start_line,
+            "startColumn": start_column,
+            "endLine": end_line,
+            "endColumn": end_column,
+            "byteOffset": label.range.start,
+            "byteLength": label.range.len(),
+        }),
+    );
+    location.into()
+}
+
+#[test]
+fn sarif_maps_labels_to_locations() {
+    use crate::{ChildLabel, Label, ReportBuilder};
+
+    let mut first = ReportBuilder::new("let x = 1;")
+        .with_source_name("src/main.rs")
+        .with_severity(Severity::Warning)
+        .with_code("W001")
+        .with_title("unused variable");
+    first.push(
+        Label::new(4..=4, "never read")
+            .with_child_label(ChildLabel::new("prefix it with an underscore")),
+    );
+    let mut second = ReportBuilder::new("fn f() {}\nfn f() {}").with_severity(Severity::Help);
+    second.push(Label::new(13..=13, "defined twice"));
+
+    let mut log = SarifLog::new("linter").with_tool_version("1.0.0");
+    log.push_iter(&[first.finish().unwrap(), second.finish().unwrap()]);
+    let sarif = log.to_value();
+    assert_eq!(sarif["version"], "2.1.0");
+    let run = &sarif["runs"][0];
+    assert_eq!(run["tool"]["driver"]["rules"], json!([{ "id": "W001" }]));
+
+    let result = &run["results"][0];
+    assert_eq!(result["ruleId"], "W001");
+    assert_eq!(result["level"], "warning");
+    assert_eq!(result["message"]["text"], "unused variable");
+    let location = &result["locations"][0];
+    assert_eq!(
+        location["physicalLocation"]["artifactLocation"]["uri"],
+        "src/main.rs"
+    );
+    assert_eq!(
+        location["physicalLocation"]["region"],
+        json!({
+            "startLine": 1, "startColumn": 5, "endLine": 1, "endColumn": 6,
+            "byteOffset": 4, "byteLength": 1,
+        })
+    );
+    assert_eq!(location["message"]["text"], "never read");
+    assert_eq!(
+        result["relatedLocations"][0]["message"]["text"],
+        "prefix it with an underscore"
+    );
+
+    let result = &run["results"][1];
+    assert_eq!(result["level"], "note");
+    assert_eq!(result["message"]["text"], "defined twice");
+    assert_eq!(
+        result["locations"][0]["physicalLocation"]["region"]["startLine"],
+        2
+    );
+}
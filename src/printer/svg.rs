@@ -0,0 +1,147 @@
+use super::{
+    html::escape_html,
+    runs::{RunStyle, hex, styled_rows},
+    *,
+};
+
+/// Font size of the text, in pixels
+const FONT_SIZE: f32 = 14.0;
+/// Width of a single terminal cell, roughly the advance of a monospace font of that size
+const CELL_WIDTH: f32 = 8.4;
+const LINE_HEIGHT: f32 = 18.0;
+/// Space between the border of the image and the text
+const PADDING: f32 = 12.0;
+const BACKGROUND: &str = "#1e1e1e";
+const FOREGROUND: &str = "#d4d4d4";
+
+impl Report {
+    /// Writes the report as a standalone SVG image, resembling a dark terminal
+    ///
+    /// Every run of text is placed onto a monospace grid by its terminal columns,
+    /// so the carets line up even if the font used to display the image is not quite monospace.
+    /// Colors are always drawn in full, no matter the color depth of the report.
+    pub fn write_svg<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let lines = styled_rows(&self.rows()?);
+        let columns = lines
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|run| width::display_width(&run.text))
+                    .sum::<usize>()
+            })
+            .max()
+            .unwrap_or_default();
+        let width = columns as f32 * CELL_WIDTH + 2.0 * PADDING;
+        let height = lines.len() as f32 * LINE_HEIGHT + 2.0 * PADDING;
+
+        let mut texts = String::new();
+        lines.iter().enumerate().for_each(|(row, line)| {
+            let top = PADDING + row as f32 * LINE_HEIGHT;
+            // The baseline, leaving the same space above and below the glyphs
+            let baseline = top + (LINE_HEIGHT + FONT_SIZE) / 2.0 - 2.0;
+            let mut column = 0;
+            let mut spans = String::new();
+            line.iter().for_each(|run| {
+                let x = PADDING + column as f32 * CELL_WIDTH;
+                let cells = width::display_width(&run.text);
+                column += cells;
+                if run.text.trim().is_empty() {
+                    // Nothing to draw, the position of the next run is given explicitly anyways
+                    return;
+                }
+                spans.push_str(&format!(
+                    "<tspan x=\"{x:.1}\" textLength=\"{:.1}\" lengthAdjust=\"spacingAndGlyphs\"{}>",
+                    cells as f32 * CELL_WIDTH,
+                    run.style.svg_attributes()
+                ));
+                escape_html(&run.text, &mut spans);
+                spans.push_str("</tspan>");
+            });
+            if !spans.is_empty() {
+                texts.push_str(&format!("<text y=\"{baseline:.1}\">{spans}</text>\n"));
+            }
+        });
+
+        write!(
+            writer,
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.1}\" height=\"{height:.1}\" ",
+                "viewBox=\"0 0 {width:.1} {height:.1}\" font-family=\"monospace\" font-size=\"{font_size:.1}\" ",
+                "fill=\"{foreground}\" xml:space=\"preserve\">\n",
+                "<rect width=\"100%\" height=\"100%\" fill=\"{background}\"/>\n",
+                "{texts}</svg>\n",
+            ),
+            width = width,
+            height = height,
+            font_size = FONT_SIZE,
+            foreground = FOREGROUND,
+            background = BACKGROUND,
+            texts = texts,
+        )
+    }
+}
+
+impl RunStyle {
+    /// The presentation attributes of a `<tspan>`, each preceded by a space
+    fn svg_attributes(&self) -> String {
+        let mut attributes = String::new();
+        if let Some(color) = &self.color {
+            attributes.push_str(&format!(" fill=\"{}\"", hex(color)));
+        }
+        if self.bold {
+            attributes.push_str(" font-weight=\"bold\"");
+        }
+        if self.dim {
+            attributes.push_str(" opacity=\"0.7\"");
+        }
+        if self.italic {
+            attributes.push_str(" font-style=\"italic\"");
+        }
+        match (self.underline, self.strikethrough) {
+            (true, true) => attributes.push_str(" text-decoration=\"underline line-through\""),
+            (true, false) => attributes.push_str(" text-decoration=\"underline\""),
+            (false, true) => attributes.push_str(" text-decoration=\"line-through\""),
+            (false, false) => {}
+        }
+        if self.hidden {
+            attributes.push_str(" visibility=\"hidden\"");
+        }
+        attributes
+    }
+}
+
+#[test]
+fn svg_places_runs_on_the_grid() {
+    use crate::{Label, ReportBuilder, Trim};
+
+    let mut report = ReportBuilder::new("if a < b {}").trim_input(Trim::None);
+    report.push(Label::new(5..=6, "compared here").with_color(AnsiStyle::RED));
+    let mut svg = Vec::new();
+    report.finish().unwrap().write_svg(&mut svg).unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert!(svg.contains(">if a &lt; b {}</tspan>"));
+    // The message follows the "╰─▶ " starting in column 5
+    let message = format!(
+        "<tspan x=\"{:.1}\" textLength=\"{:.1}\" lengthAdjust=\"spacingAndGlyphs\" fill=\"#c50f0c\">",
+        PADDING + 9.0 * CELL_WIDTH,
+        13.0 * CELL_WIDTH
+    );
+    assert!(
+        svg.contains(&format!("{message}compared here</tspan>")),
+        "{svg}"
+    );
+    assert_eq!(svg.matches("<text ").count(), 4);
+
+    // Styles without an equivalent are skipped, rather than dropping the others
+    let mut report = ReportBuilder::new("if a < b {}")
+        .trim_input(Trim::None)
+        .with_color_depth(ColorDepth::None);
+    let message = format!("{}fast", AnsiStyle::BLINK);
+    report.push(Label::new(5..=6, message).with_color(AnsiStyle::RED));
+    let mut svg = Vec::new();
+    report.finish().unwrap().write_svg(&mut svg).unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+    assert!(svg.contains(" fill=\"#c50f0c\">fast</tspan>"), "{svg}");
+}
@@ -1,9 +1,23 @@
-use ::token::saturating::SaturatingArithmetic;
+use ::token::{saturating::SaturatingArithmetic, width};
 
 use super::*;
 
+/// Maps a byte offset of the untrimmed line onto the trimmed input,
+/// which is prepended by "... " if anything was trimmed off its start
+pub(super) fn rebase_trimmed(input_label_offset: usize, index: usize) -> usize {
+    if input_label_offset > 0 {
+        index.saturating_sub(input_label_offset).sat_add(4)
+    } else {
+        index
+    }
+}
+
 impl Report {
+    /// The label ranges are byte offsets into the untrimmed line,
+    /// whereas the carets are positioned by the terminal columns of the (trimmed) input
     pub(super) fn generate_underbar(
+        line: usize,
+        input: &str,
         input_label_offset: usize,
         labels: impl IntoIterator<Item = TokenizedLabelFull>,
     ) -> ReportLabels {
@@ -13,16 +27,20 @@ impl Report {
         let labels = labels.into_iter().map(|label| {
             let TokenizedLabelFull {
                 range,
+                source_range,
                 message,
                 child_labels,
                 #[cfg(feature = "caret_color")]
                 caret_color,
             } = label;
 
-            // If a offset is set, the input is prepended by 3x. and a space
-            let offset = offset.saturating_sub(4);
-            let start = range.start().saturating_sub(offset);
-            let end = range.end().saturating_sub(offset);
+            let rebase = |index: usize| rebase_trimmed(offset, index);
+            let start = width::columns_of(input, rebase(range.start())).start;
+            // The end is inclusive, so it covers all columns of its grapheme
+            let end = width::columns_of(input, rebase(range.end()))
+                .end
+                .sat_sub(1)
+                .max(start);
 
             let underbar_range: RangeInclusive = (start..=end).into();
 
@@ -33,6 +51,8 @@ impl Report {
                 usize,
                 // underbar_range
                 RangeInclusive,
+                // source_range
+                RangeInclusive,
                 // caret_positionals
                 Vec<(
                     // caret_position (relative to start)
@@ -47,6 +67,7 @@ impl Report {
             ) = (
                 start,
                 underbar_range,
+                source_range,
                 // Generate the underbar line positionals
                 // It is important that this is generated first, as multiple labels can overlap and we cannot change after printing
                 vec![(
@@ -74,6 +95,7 @@ impl Report {
         let mut new_labels: Vec<(
             usize,
             RangeInclusive,
+            RangeInclusive,
             Vec<(usize, usize, TokenizedLabel, Vec<TokenizedChildLabel>)>,
         )> = Vec::new();
 
@@ -82,19 +104,32 @@ impl Report {
             |mut current: Option<(
                 usize,
                 RangeInclusive,
+                RangeInclusive,
                 Vec<(usize, usize, TokenizedLabel, Vec<TokenizedChildLabel>)>,
             )>,
              other| {
-                if let Some((current_start, current_range, mut current_labels)) = current.take() {
-                    let (other_start, other_range, other_labels) = other;
+                if let Some((current_start, current_range, current_source, mut current_labels)) =
+                    current.take()
+                {
+                    let (other_start, other_range, other_source, other_labels) = other;
+                    // A merged caret covers the source ranges of both
+                    let merged_source: RangeInclusive =
+                        (current_source.start().min(other_source.start())
+                            ..=current_source.end().max(other_source.end()))
+                            .into();
 
                     #[cfg(not(feature = "merge_overlap"))]
                     {
                         // If both starting positions are the same, we split them into two separate carets
                         // as this improved readability tremendously
                         if other_start == current_start {
-                            new_labels.push((current_start, current_range, current_labels));
-                            return Some((other_start, other_range, other_labels));
+                            new_labels.push((
+                                current_start,
+                                current_range,
+                                current_source,
+                                current_labels,
+                            ));
+                            return Some((other_start, other_range, other_source, other_labels));
                         }
                     }
                     // If "other" fits entirely within the current range, we remove "other" and merge it into the current range, adding its caret positions
@@ -107,7 +142,7 @@ impl Report {
                                 (pos.sat_add(offset), len, label_message, child_labels)
                             },
                         ));
-                        Some((current_start, current_range, current_labels))
+                        Some((current_start, current_range, merged_source, current_labels))
                     }
                     // If both overlap, we remove "other" and merge them into one, extending the current range to encompass both, and adding caret positions
                     else if (other_start < (current_start.sat_add(current_range.len())))
@@ -124,14 +159,19 @@ impl Report {
                         // Extend the current range to encompass both
                         let new_end = std::cmp::max(current_range.end(), other_range.end());
                         let new_range = (current_range.start()..=new_end).into();
-                        Some((current_start, new_range, current_labels))
+                        Some((current_start, new_range, merged_source, current_labels))
                     } else {
                         // No overlap, just add the current range as-is and move to the next
-                        new_labels.push((current_start, current_range, current_labels));
-                        Some((other_start, other_range, other_labels))
+                        new_labels.push((
+                            current_start,
+                            current_range,
+                            current_source,
+                            current_labels,
+                        ));
+                        Some((other_start, other_range, other_source, other_labels))
                     }
                 } else {
-                    Some((other.0, other.1, other.2))
+                    Some(other)
                 }
             },
         ) {
@@ -173,14 +213,84 @@ impl Report {
 
         new_labels
             .into_iter()
-            .map(|(start, range, positions)| {
+            .map(|(start, range, source_range, positions)| {
                 let end = range.end();
                 ReportCaret::new(
+                    line,
                     start,
                     end,
                     positions.into_iter().map(ReportLabel::from).collect(),
                 )
+                .with_source_range(source_range)
             })
             .collect()
     }
 }
+
+/// Renders a single label and returns the input line and the underbar line
+#[cfg(test)]
+fn render_underbar<R: IntoRange>(input: &str, range: R, trim: Trim) -> (String, String) {
+    let mut report = ReportBuilder::new(input).trim_input(trim);
+    report.push(Label::new(range, "label"));
+    let mut output = Vec::new();
+    report.finish().unwrap().write(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let mut lines = output.lines().map(str::to_string);
+    (lines.next().unwrap(), lines.next().unwrap())
+}
+
+#[cfg(test)]
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+#[test]
+fn underbar_columns_non_ascii() {
+    // Wide characters: "日本" takes 6 bytes but 4 columns
+    let (input, underbar) = render_underbar("日本 = value", 9..=13, Trim::None);
+    assert_eq!(input, "日本 = value");
+    assert_eq!(indent_of(&underbar), 7);
+
+    // Combining characters: "e\u{301}" takes 3 bytes but a single column
+    let (_, underbar) = render_underbar("cafe\u{301} = 1", 9..=9, Trim::None);
+    assert_eq!(indent_of(&underbar), 7);
+
+    // Trimming must not split the emoji in front of the label
+    let (input, underbar) = render_underbar("🦀🦀🦀 crab", 13..=16, Trim::new_by_chars(2));
+    assert_eq!(input, "... 🦀 crab");
+    assert_eq!(indent_of(&underbar), 7);
+}
+
+#[test]
+fn underbar_columns_tabs() {
+    // Tabs are expanded to the next multiple of the (default) tab width of 4
+    let (input, underbar) = render_underbar("all:\n\tcc -o main main.c", 9..=12, Trim::None);
+    assert_eq!(input, "    cc -o main main.c");
+    assert_eq!(indent_of(&underbar), 7);
+
+    let (input, underbar) = render_underbar("id\tname\tvalue", 8..=12, Trim::None);
+    assert_eq!(input, "id  name    value");
+    assert_eq!(indent_of(&underbar), 12);
+}
+
+#[test]
+fn colored_input_non_ascii() {
+    let mut report = ReportBuilder::new("名前 = \"値\"")
+        .trim_input(Trim::None)
+        .colored_input(true);
+    report.push(Label::new(9..=13, "label").with_color(AnsiStyle::RED));
+    let mut output = Vec::new();
+    report.finish().unwrap().write(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    // Strip the escape sequences, no part of the input may be skipped
+    let mut input = String::new();
+    let mut chars = output.lines().next().unwrap().chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| *c == 'm');
+        } else {
+            input.push(c);
+        }
+    }
+    assert_eq!(input, "名前 = \"値\"");
+}
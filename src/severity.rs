@@ -0,0 +1,83 @@
+use ::std::fmt::Display;
+
+use ::token::AnsiStyle;
+
+/// The severity of a report, printed at the start of its header
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+    Note,
+    Help,
+    /// A severity not covered by the other variants, e.g. "lint" or "info"
+    Custom {
+        name: String,
+        style: AnsiStyle,
+    },
+}
+
+impl Severity {
+    pub fn custom<I: Display, S: Into<AnsiStyle>>(name: I, style: S) -> Self {
+        Self::Custom {
+            name: name.to_string(),
+            style: style.into(),
+        }
+    }
+
+    /// The name as printed in the header
+    pub fn name(&self) -> &str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+            Severity::Help => "help",
+            Severity::Custom { name, .. } => name,
+        }
+    }
+
+    /// The default style used to print the severity (and code) in the header
+    pub fn style(&self) -> AnsiStyle {
+        match self {
+            Severity::Error => AnsiStyle::BRIGHT_RED,
+            Severity::Warning => AnsiStyle::BRIGHT_YELLOW,
+            Severity::Note => AnsiStyle::BRIGHT_GREEN,
+            Severity::Help => AnsiStyle::BRIGHT_CYAN,
+            Severity::Custom { style, .. } => *style,
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Serialized by its name, as custom styles are of no use outside of a terminal
+#[cfg(feature = "serde")]
+impl serde::Serialize for Severity {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[test]
+fn severity_names_and_styles() {
+    let defaults = [
+        (Severity::Error, "error", AnsiStyle::BRIGHT_RED),
+        (Severity::Warning, "warning", AnsiStyle::BRIGHT_YELLOW),
+        (Severity::Note, "note", AnsiStyle::BRIGHT_GREEN),
+        (Severity::Help, "help", AnsiStyle::BRIGHT_CYAN),
+    ];
+    defaults.into_iter().for_each(|(severity, name, style)| {
+        assert_eq!(severity.to_string(), name);
+        assert_eq!(severity.style(), style);
+    });
+    assert_eq!(Severity::default(), Severity::Error);
+
+    let lint = Severity::custom("lint", AnsiStyle::BRIGHT_BLUE);
+    assert_eq!(lint.name(), "lint");
+    assert_eq!(lint.style(), AnsiStyle::BRIGHT_BLUE);
+    assert_ne!(lint, Severity::custom("lint", AnsiStyle::BLUE));
+}
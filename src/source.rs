@@ -0,0 +1,324 @@
+use ::std::{borrow::Cow, fmt::Display, ops::Deref, sync::Arc};
+
+use ::token::{saturating::SaturatingArithmetic, width};
+
+use crate::RangeInclusive;
+
+/// Identifies a source within a `Sources` registry
+///
+/// The default id refers to the first source added, which is the one labels point into by default.
+/// An id is nothing but the index of the source within its registry, it is not tied to the registry itself:
+/// An id handed out by another `Sources` is only rejected (`Error::UnknownFile`) if it is out of bounds,
+/// otherwise it silently refers to whichever source has the same index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FileId(usize);
+
+/// The text of a source, either borrowed, shared or owned
+///
+/// Reports only copy the lines they print, so borrowing (or sharing) a large input
+/// allows rendering many reports against it without ever copying it as a whole.
+#[derive(Debug, Clone)]
+pub enum SourceText<'src> {
+    Borrowed(&'src str),
+    Shared(Arc<str>),
+    Owned(String),
+}
+impl Deref for SourceText<'_> {
+    type Target = str;
+    fn deref(&self) -> &str {
+        match self {
+            SourceText::Borrowed(text) => text,
+            SourceText::Shared(text) => text,
+            SourceText::Owned(text) => text,
+        }
+    }
+}
+impl<'src> From<&'src str> for SourceText<'src> {
+    fn from(text: &'src str) -> Self {
+        Self::Borrowed(text)
+    }
+}
+impl<'src> From<&'src String> for SourceText<'src> {
+    fn from(text: &'src String) -> Self {
+        Self::Borrowed(text)
+    }
+}
+impl From<Arc<str>> for SourceText<'_> {
+    fn from(text: Arc<str>) -> Self {
+        Self::Shared(text)
+    }
+}
+impl From<String> for SourceText<'_> {
+    fn from(text: String) -> Self {
+        Self::Owned(text)
+    }
+}
+impl From<Box<str>> for SourceText<'_> {
+    fn from(text: Box<str>) -> Self {
+        Self::Owned(text.into())
+    }
+}
+impl<'src> From<Cow<'src, str>> for SourceText<'src> {
+    fn from(text: Cow<'src, str>) -> Self {
+        match text {
+            Cow::Borrowed(text) => Self::Borrowed(text),
+            Cow::Owned(text) => Self::Owned(text),
+        }
+    }
+}
+
+/// A single source, with an optional name (e.g. a path or `<stdin>`)
+#[derive(Debug, Clone)]
+pub struct SourceFile<'src> {
+    pub(crate) name: Option<String>,
+    pub(crate) input: SourceText<'src>,
+}
+impl<'src> SourceFile<'src> {
+    pub fn new<I: Into<SourceText<'src>>>(input: I) -> Self {
+        Self {
+            name: None,
+            input: input.into(),
+        }
+    }
+    pub fn with_name<N: Display>(mut self, name: N) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+}
+
+/// A registry of sources, so that a single report can annotate multiple files
+///
+/// Labels point into a source by its `FileId`, see `Label::in_file`
+#[derive(Debug, Clone, Default)]
+pub struct Sources<'src> {
+    files: Vec<SourceFile<'src>>,
+}
+impl<'src> Sources<'src> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a named source and returns the id to reference it by
+    pub fn add<N: Display, I: Into<SourceText<'src>>>(&mut self, name: N, input: I) -> FileId {
+        self.push(SourceFile::new(input).with_name(name))
+    }
+    /// Adds a source and returns the id to reference it by
+    pub fn push(&mut self, file: SourceFile<'src>) -> FileId {
+        self.files.push(file);
+        FileId(self.files.len().sat_sub(1))
+    }
+    pub fn get(&self, id: FileId) -> Option<&SourceFile<'src>> {
+        self.files.get(id.0)
+    }
+    pub fn get_mut(&mut self, id: FileId) -> Option<&mut SourceFile<'src>> {
+        self.files.get_mut(id.0)
+    }
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// A line index over a source string
+///
+/// Stores the byte offset at which each line starts, so that a byte offset
+/// (as used by the label ranges) can be mapped to the line it belongs to.
+#[derive(Debug, Clone)]
+pub(crate) struct SourceLines<'a> {
+    source: &'a str,
+    /// Byte offset of the first character of each line
+    starts: Vec<usize>,
+}
+
+impl<'a> SourceLines<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(pos, _)| pos.sat_add(1)))
+            .collect();
+        Self { source, starts }
+    }
+
+    /// The whole source string
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Returns the (0-based) line that contains the given byte offset
+    ///
+    /// Offsets past the end of the source are attributed to the last line
+    pub fn line_of(&self, offset: usize) -> usize {
+        match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next.saturating_sub(1),
+        }
+    }
+
+    /// Returns the (0-based) line and column of the given byte offset
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_of(offset);
+        (line, offset.sat_sub(self.line_start(line)))
+    }
+
+    /// Byte offset of the first character of the given line
+    pub fn line_start(&self, line: usize) -> usize {
+        self.starts[line]
+    }
+
+    /// Byte range of the given line, excluding the line terminator
+    pub fn line_range(&self, line: usize) -> std::ops::Range<usize> {
+        let start = self.starts[line];
+        let end = self
+            .starts
+            .get(line.sat_add(1))
+            // Exclude the '\n' of the line
+            .map(|next| next.saturating_sub(1))
+            .unwrap_or(self.source.len());
+        // Exclude the '\r' of "\r\n" terminated lines
+        let end = if end > start && self.source.as_bytes()[end.saturating_sub(1)] == b'\r' {
+            end.saturating_sub(1)
+        } else {
+            end
+        };
+        start..end
+    }
+
+    /// The text of the given line, excluding the line terminator
+    pub fn line(&self, line: usize) -> &'a str {
+        &self.source[self.line_range(line)]
+    }
+
+    /// Returns the (0-based) lines the start and the end of the given absolute range are located on
+    ///
+    /// A range ending on a line terminator is considered to end on the line it terminates
+    pub fn span(&self, range: RangeInclusive) -> (usize, usize) {
+        (self.line_of(range.start()), self.line_of(range.end()))
+    }
+
+    /// Rebases the given absolute range onto the line its start is located on
+    ///
+    /// Returns the line and the range relative to the start of that line.
+    /// Ranges that reach into the following lines are clamped to the end of their first line,
+    /// whereas ranges reaching past the end of the source are left as-is.
+    pub fn rebase(&self, range: RangeInclusive) -> (usize, RangeInclusive) {
+        let line = self.line_of(range.start());
+        let line_start = self.line_start(line);
+        let line_end = self.line_range(line).end;
+        let end = if range.end() >= line_end && range.end() < self.source.len() {
+            // Points at the line terminator at most
+            line_end
+        } else {
+            range.end()
+        };
+        (
+            line,
+            (range.start().saturating_sub(line_start)..=end.saturating_sub(line_start)).into(),
+        )
+    }
+}
+
+/// A single line with its tabs expanded to spaces
+///
+/// Tabs advance to the next multiple of the tab width, counted in columns from the start of the line.
+/// Offsets into the original line can be mapped onto the expanded one.
+#[derive(Debug, Clone)]
+pub(crate) struct ExpandedLine {
+    text: String,
+    /// Byte offset of each tab within the original line, with the number of bytes its expansion adds
+    tabs: Vec<(usize, usize)>,
+}
+
+impl ExpandedLine {
+    /// A tab width of 0 is treated as 1
+    pub fn new(line: &str, tab_width: usize) -> Self {
+        let tab_width = tab_width.max(1);
+        let mut text = String::with_capacity(line.len());
+        let mut tabs = Vec::new();
+        let mut column = 0;
+        let mut offset = 0;
+        let mut parts = line.split('\t');
+        if let Some(part) = parts.next() {
+            text.push_str(part);
+            column = width::display_width(part);
+            offset = part.len();
+        }
+        parts.for_each(|part| {
+            let spaces = tab_width.sat_sub(column % tab_width);
+            tabs.push((offset, spaces.sat_sub(1)));
+            text.extend(std::iter::repeat_n(' ', spaces));
+            text.push_str(part);
+            column = column.sat_add(spaces).sat_add(width::display_width(part));
+            // The tab itself is a single byte
+            offset = offset.sat_add(1).sat_add(part.len());
+        });
+        Self { text, tabs }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Maps a byte offset of the original line onto the expanded line
+    ///
+    /// Offsets pointing at a tab are mapped onto the first space of its expansion
+    pub fn offset(&self, offset: usize) -> usize {
+        self.tabs
+            .iter()
+            .take_while(|(tab, _)| *tab < offset)
+            .fold(offset, |offset, (_, added)| offset.sat_add(*added))
+    }
+
+    /// Maps an inclusive range of the original line onto the expanded line,
+    /// covering all spaces of a tab the range ends on
+    pub fn range(&self, range: RangeInclusive) -> RangeInclusive {
+        let start = self.offset(range.start());
+        let end = self.offset(range.end().sat_add(1)).sat_sub(1).max(start);
+        (start..=end).into()
+    }
+}
+
+#[test]
+fn expanded_line_offsets() {
+    let line = ExpandedLine::new("a\tb\t\tc", 4);
+    assert_eq!(line.text(), "a   b       c");
+    assert_eq!(line.offset(0), 0);
+    // The first tab and the character following it
+    assert_eq!(line.offset(1), 1);
+    assert_eq!(line.offset(2), 4);
+    // Both of the consecutive tabs
+    assert_eq!(line.offset(3), 5);
+    assert_eq!(line.offset(4), 8);
+    assert_eq!(line.offset(5), 12);
+    // A label on the first tab covers all of its spaces
+    assert_eq!(line.range((1..=1).into()), (1..=3).into());
+    assert_eq!(line.range((2..=2).into()), (4..=4).into());
+    // Wide characters count with their display width
+    assert_eq!(ExpandedLine::new("日\tx", 4).text(), "日  x");
+}
+
+#[test]
+fn source_lines_rebase() {
+    let source = "first line\nsecond line\r\nthird";
+    let lines = SourceLines::new(source);
+    assert_eq!(lines.line(0), "first line");
+    assert_eq!(lines.line(1), "second line");
+    assert_eq!(lines.line(2), "third");
+    assert_eq!(lines.line_of(0), 0);
+    assert_eq!(lines.line_of(10), 0);
+    assert_eq!(lines.line_of(11), 1);
+    assert_eq!(lines.line_of(source.len()), 2);
+    assert_eq!(lines.line_col(18), (1, 7));
+    // "line" in the second line
+    assert_eq!(lines.rebase((18..=21).into()), (1, (7..=10).into()));
+    // Reaches into the third line, so its clamped to the end of the second line
+    assert_eq!(lines.rebase((18..=27).into()), (1, (7..=11).into()));
+    assert_eq!(lines.span((18..=27).into()), (1, 2));
+    // Ends on the terminator of the second line
+    assert_eq!(lines.span((18..=23).into()), (1, 1));
+}
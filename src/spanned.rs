@@ -0,0 +1,127 @@
+use ::std::error::Error;
+
+use crate::{ChildLabel, Label, RangeInclusive, ReportBuilder, SourceText};
+
+/// A value (usually an error) that refers to a part of the source
+pub trait Spanned {
+    /// The byte range of the source, see `IntoRange` for the accepted ranges
+    fn span(&self) -> RangeInclusive;
+}
+impl<T: Spanned + ?Sized> Spanned for &T {
+    fn span(&self) -> RangeInclusive {
+        (**self).span()
+    }
+}
+impl<T: Spanned + ?Sized> Spanned for Box<T> {
+    fn span(&self) -> RangeInclusive {
+        (**self).span()
+    }
+}
+
+/// How the `source()` chain of an error is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ErrorChain {
+    /// Each source becomes a child label of the errors label
+    #[default]
+    ChildLabels,
+    /// Each source becomes a note after the caret blocks
+    Notes,
+}
+
+/// Every error of the `source()` chain, excluding the error itself
+fn sources<'e, E: Error + ?Sized>(error: &'e E) -> impl Iterator<Item = &'e (dyn Error + 'static)> {
+    std::iter::successors(error.source(), |&source| source.source())
+}
+
+/// The span of the error, an empty span (e.g. at the end of the input) pointing at a single column
+fn span<E: Spanned + ?Sized>(error: &E) -> RangeInclusive {
+    let span = error.span();
+    span.max_end(span.start())
+}
+
+impl Label {
+    /// Annotates the span of the error with its message, its sources becoming child labels
+    pub fn from_error<E: Error + Spanned + ?Sized>(error: &E) -> Self {
+        sources(error).fold(Label::new(span(error), error), |label, source| {
+            label.with_child_label(ChildLabel::new(source))
+        })
+    }
+}
+
+impl<'src> ReportBuilder<'src> {
+    /// Creates a builder annotating the span of the error within the input
+    ///
+    /// The label carries the message of the error, while its sources are added as given by `chain`.
+    pub fn from_error<I: Into<SourceText<'src>>, E: Error + Spanned + ?Sized>(
+        input: I,
+        error: &E,
+        chain: ErrorChain,
+    ) -> Self {
+        let mut report = Self::new(input);
+        match chain {
+            ErrorChain::ChildLabels => {
+                report.push(Label::from_error(error));
+                report
+            }
+            ErrorChain::Notes => {
+                report.push(Label::new(span(error), error));
+                sources(error).fold(report, |report, source| report.with_note(source))
+            }
+        }
+    }
+}
+
+#[test]
+fn error_chain_becomes_child_labels_or_notes() {
+    use crate::IntoRange;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("invalid port number")]
+    struct InvalidPort {
+        at: std::ops::Range<usize>,
+        #[source]
+        source: std::num::ParseIntError,
+    }
+    impl Spanned for InvalidPort {
+        fn span(&self) -> RangeInclusive {
+            self.at.clone().into_range()
+        }
+    }
+
+    let input = "port = 80x";
+    let error = InvalidPort {
+        at: 7..10,
+        source: "80x".parse::<u16>().unwrap_err(),
+    };
+    let render = |report: ReportBuilder| report.plain_text(true).finish().unwrap().to_string();
+
+    let label = Label::from_error(&error);
+    assert_eq!(label.range, (7..=9).into());
+    assert_eq!(
+        label.child_labels[0].message,
+        "invalid digit found in string"
+    );
+    let children = render(ReportBuilder::from_error(
+        input,
+        &error,
+        ErrorChain::ChildLabels,
+    ));
+    assert!(children.contains("invalid port number") && children.contains("invalid digit"));
+    assert!(!children.contains("note"));
+
+    let notes = render(ReportBuilder::from_error(input, &error, ErrorChain::Notes));
+    assert!(notes.contains("note: invalid digit found in string"));
+    // Boxed errors are spanned just as well
+    let boxed: Box<InvalidPort> = Box::new(error);
+    assert_eq!(Label::from_error(&boxed).range, (7..=9).into());
+
+    // Empty spans, e.g. of an unexpected end of input, point at a single column
+    let error = InvalidPort {
+        at: 10..10,
+        source: "".parse::<u16>().unwrap_err(),
+    };
+    assert_eq!(Label::from_error(&error).range, (10..=10).into());
+    let report = ReportBuilder::from_error(input, &error, ErrorChain::Notes);
+    let notes = render(report.trim_input(crate::Trim::None));
+    assert!(notes.starts_with("port = 80x\n          ┬\n"), "{notes}");
+}
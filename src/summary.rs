@@ -0,0 +1,110 @@
+use ::std::ops::Range;
+
+use ::token::{ColorDepth, saturating::SaturatingArithmetic};
+
+use crate::{Severity, source::SourceLines};
+
+/// The structured content of a report, as known to the builder
+///
+/// Unlike the rendered output it keeps the original byte ranges of the labels,
+/// so that tools (editors, CI annotators, ...) do not have to recover them from the terminal output.
+/// All messages have their styles removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ReportSummary {
+    /// Defaults to `Severity::Error`, even if the report has no header
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub title: Option<String>,
+    /// In the order they were added to the builder
+    pub labels: Vec<LabelSummary>,
+    /// Notes, help messages and any other footers
+    pub footers: Vec<FooterSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LabelSummary {
+    /// The name of the source the label points into, if it was given one
+    pub source: Option<String>,
+    /// The (exclusive) byte range within that source
+    pub range: Range<usize>,
+    /// The position of the first annotated character
+    pub start: Position,
+    /// The position right after the last annotated character
+    pub end: Position,
+    pub message: String,
+    pub children: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FooterSummary {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// A position within a source, as shown by editors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Position {
+    /// The 1-based line
+    pub line: usize,
+    /// The 1-based column, counted in characters
+    pub column: usize,
+    /// The 1-based column, counted in UTF-16 code units as expected by LSP clients
+    pub utf16_column: usize,
+}
+impl Position {
+    /// Offsets past the end of a line are counted as if the line continued
+    pub(crate) fn new(source: &SourceLines, offset: usize) -> Self {
+        let (line, column) = source.line_col(offset);
+        let text = source.line(line);
+        let past_end = column.saturating_sub(text.len());
+        let chars = text
+            .char_indices()
+            .take_while(|(index, _)| *index < column)
+            .map(|(_, c)| c);
+        let utf16_column = chars.clone().map(char::len_utf16).sum::<usize>();
+        Self {
+            line: line.sat_add(1),
+            column: chars.count().sat_add(past_end).sat_add(1),
+            utf16_column: utf16_column.sat_add(past_end).sat_add(1),
+        }
+    }
+}
+
+/// Removes all escape sequences, which were either styles or would otherwise garble the output
+pub(crate) fn strip_styles(message: &str) -> String {
+    ColorDepth::None.convert(message)
+}
+
+#[test]
+fn summary_keeps_ranges() {
+    use crate::{ChildLabel, Label, ReportBuilder, Sources};
+
+    let mut sources = Sources::new();
+    sources.add("main.rs", "fn main() {\n    let ä = 1;\n}");
+    let mut report = ReportBuilder::from_sources(sources).with_title("unused variable");
+    report.push(
+        Label::new(20..=21, "\u{1b}[1mnever\u{1b}[0m used")
+            .with_child_label(ChildLabel::new("prefix it with an underscore")),
+    );
+    let report = report.finish().unwrap();
+    let summary = report.summary();
+    assert_eq!(summary.severity, Severity::Error);
+    assert_eq!(summary.title.as_deref(), Some("unused variable"));
+    let label = &summary.labels[0];
+    assert_eq!(label.source.as_deref(), Some("main.rs"));
+    assert_eq!(label.range, 20..22);
+    // "ä" takes two bytes, but only a single column
+    let position = |column| Position {
+        line: 2,
+        column,
+        utf16_column: column,
+    };
+    assert_eq!(label.start, position(9));
+    assert_eq!(label.end, position(10));
+    assert_eq!(label.message, "never used");
+    assert_eq!(label.children, ["prefix it with an underscore"]);
+}
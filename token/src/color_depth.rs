@@ -0,0 +1,287 @@
+//! Downgrading colors to what the terminal is able to display
+//!
+//! Colors are always generated as 24-bit escapes, the conversion happens right before they are emitted.
+
+use ::std::io::IsTerminal;
+
+use super::*;
+use crate::saturating::SaturatingArithmetic;
+
+/// The amount of colors a terminal is able to display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ColorDepth {
+    /// No escape sequences at all, e.g. when writing into a file
+    None,
+    /// The 16 basic colors (`ESC[31m`, `ESC[91m`, ...)
+    Ansi16,
+    /// The xterm-256 palette (`ESC[38;5;<n>m`)
+    Ansi256,
+    /// 24-bit colors (`ESC[38;2;<r>;<g>;<b>m`)
+    #[default]
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Detects the color depth of the given stream from the environment
+    ///
+    /// See `ColorDepth::from_env` for the variables that are taken into account.
+    pub fn detect<T: IsTerminal>(stream: &T) -> Self {
+        Self::from_env(
+            |name| std::env::var_os(name).map(|value| value.to_string_lossy().into_owned()),
+            stream.is_terminal(),
+        )
+    }
+
+    /// Determines the color depth from the given environment variables
+    ///
+    /// - `NO_COLOR` (if not empty) disables colors, no matter what
+    /// - `CLICOLOR_FORCE` (if not empty or "0") enables colors, even if the output is not a terminal
+    /// - `COLORTERM` being "truecolor" or "24bit" enables 24-bit colors
+    /// - `TERM` being "dumb" disables colors, while "*-256color" enables the xterm-256 palette
+    ///
+    /// Otherwise terminals get the 16 basic colors and everything else none.
+    pub fn from_env<F: Fn(&str) -> Option<String>>(var: F, is_terminal: bool) -> Self {
+        let set = |name: &str| var(name).filter(|value| !value.is_empty());
+        if set("NO_COLOR").is_some() {
+            return Self::None;
+        }
+        let forced = set("CLICOLOR_FORCE").is_some_and(|value| value != "0");
+        if !is_terminal && !forced {
+            return Self::None;
+        }
+        let term = set("TERM").unwrap_or_default();
+        if set("COLORTERM").is_some_and(|value| value == "truecolor" || value == "24bit") {
+            Self::TrueColor
+        } else if term.ends_with("-direct") {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else if term == "dumb" && !forced {
+            Self::None
+        } else {
+            Self::Ansi16
+        }
+    }
+
+    /// Rewrites all SGR escape sequences (`ESC[...m`) of the text to this depth
+    ///
+    /// Any other text, including other escape sequences, is left untouched.
+    pub fn convert(&self, text: &str) -> String {
+        if *self == Self::TrueColor {
+            return text.to_string();
+        }
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("\u{1b}[") {
+            output.push_str(&rest[..start]);
+            let params = &rest[start.sat_add(2)..];
+            let len = params
+                .find(|c: char| !c.is_ascii_digit() && c != ';')
+                .unwrap_or(params.len());
+            if params[len..].starts_with('m') {
+                output.push_str(&self.convert_sgr(&params[..len]));
+                rest = &params[len.sat_add(1)..];
+            } else {
+                // Not a style, so it is kept as is
+                output.push_str("\u{1b}[");
+                rest = params;
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+
+    /// Converts the parameters of a single SGR escape sequence into a complete sequence of this depth
+    fn convert_sgr(&self, params: &str) -> String {
+        if *self == Self::None {
+            return String::new();
+        }
+        let params = params.split(';').collect::<Vec<_>>();
+        let mut converted = Vec::with_capacity(params.len());
+        let mut index = 0;
+        while index < params.len() {
+            let start = index;
+            let param = params[start];
+            index = index.sat_add(1);
+            // Foreground colors are introduced by 38, background colors by 48
+            let offset = match param {
+                "38" => 0u8,
+                "48" => 10,
+                _ => {
+                    converted.push(param.to_string());
+                    continue;
+                }
+            };
+            let color = match params.get(index) {
+                Some(&"2") => {
+                    let rgb = params
+                        .get(index.sat_add(1)..index.sat_add(4))
+                        .and_then(|rgb| {
+                            let mut rgb = rgb.iter().map(|c| c.parse::<u8>());
+                            Some(RgbColor::new(
+                                rgb.next()?.ok()?,
+                                rgb.next()?.ok()?,
+                                rgb.next()?.ok()?,
+                            ))
+                        });
+                    index = index.sat_add(4);
+                    rgb
+                }
+                Some(&"5") => {
+                    let rgb = params
+                        .get(index.sat_add(1))
+                        .and_then(|n| n.parse::<u8>().ok())
+                        .map(RgbColor::from_ansi256);
+                    index = index.sat_add(2);
+                    rgb
+                }
+                _ => None,
+            };
+            let Some(color) = color else {
+                // Malformed, but not ours to fix, so the color is kept as it was given
+                let end = index.min(params.len());
+                converted.extend(params[start..end].iter().map(|param| param.to_string()));
+                continue;
+            };
+            converted.push(match self {
+                Self::TrueColor => format!("{};2;{};{};{}", param, color.r, color.g, color.b),
+                Self::Ansi256 => format!("{};5;{}", param, color.to_ansi256()),
+                _ => {
+                    let color = color.to_nearest_color();
+                    let code = color.color as u8 + if color.bright { 60 } else { 0 };
+                    (code + offset).to_string()
+                }
+            });
+        }
+        format!("\u{1b}[{}m", converted.join(";"))
+    }
+}
+
+/// The channel levels of the 6x6x6 color cube of the xterm-256 palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl RgbColor {
+    /// The closest color of the xterm-256 palette, either from the color cube or the grayscale ramp
+    pub fn to_ansi256(&self) -> u8 {
+        let nearest_level = |channel: u8| {
+            (0..CUBE_LEVELS.len())
+                .min_by_key(|&i| CUBE_LEVELS[i].abs_diff(channel))
+                .unwrap_or_default()
+        };
+        let (r, g, b) = (
+            nearest_level(self.r),
+            nearest_level(self.g),
+            nearest_level(self.b),
+        );
+        let cube = RgbColor::new(CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+        let cube_index = 16 + 36 * r as u8 + 6 * g as u8 + b as u8;
+
+        // The ramp goes from 8 to 238 in steps of 10
+        let average = (self.r as u16 + self.g as u16 + self.b as u16) / 3;
+        let gray_step = (average.saturating_sub(3) / 10).min(23) as u8;
+        let gray_level = 8 + 10 * gray_step;
+        let gray = RgbColor::new(gray_level, gray_level, gray_level);
+
+        if self.distance(&gray) < self.distance(&cube) {
+            232 + gray_step
+        } else {
+            cube_index
+        }
+    }
+
+    /// The color of the given index of the xterm-256 palette
+    pub fn from_ansi256(index: u8) -> Self {
+        match index {
+            0..8 => Self::from_ansi_code(30 + index),
+            8..16 => Self::from_ansi_code(90 + index - 8),
+            16..232 => {
+                let index = index - 16;
+                Some(Self::new(
+                    CUBE_LEVELS[(index / 36) as usize],
+                    CUBE_LEVELS[(index / 6 % 6) as usize],
+                    CUBE_LEVELS[(index % 6) as usize],
+                ))
+            }
+            _ => {
+                let level = 8 + 10 * (index - 232);
+                Some(Self::new(level, level, level))
+            }
+        }
+        .unwrap_or(Self::BLACK)
+    }
+
+    /// The closest of the 16 basic colors
+    pub fn to_nearest_color(&self) -> Color {
+        Color::try_from(*self).unwrap_or_else(|_| {
+            (30..38)
+                .chain(90..98)
+                .filter_map(Color::from_ansi_code)
+                .min_by_key(|color| self.distance(&RgbColor::from(*color)))
+                .unwrap_or(Color::WHITE)
+        })
+    }
+
+    /// Squared euclidean distance between two colors
+    fn distance(&self, other: &Self) -> u32 {
+        let diff = |a: u8, b: u8| (a.abs_diff(b) as u32).pow(2);
+        diff(self.r, other.r) + diff(self.g, other.g) + diff(self.b, other.b)
+    }
+}
+
+#[test]
+fn color_depth_from_env() {
+    let env = |vars: &'static [(&'static str, &'static str)]| {
+        move |name: &str| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    };
+    let truecolor = &[("COLORTERM", "truecolor"), ("TERM", "xterm-256color")];
+    assert_eq!(
+        ColorDepth::from_env(env(truecolor), true),
+        ColorDepth::TrueColor
+    );
+    assert_eq!(
+        ColorDepth::from_env(env(truecolor), false),
+        ColorDepth::None
+    );
+    let xterm = &[("TERM", "xterm-256color")];
+    assert_eq!(ColorDepth::from_env(env(xterm), true), ColorDepth::Ansi256);
+    assert_eq!(
+        ColorDepth::from_env(env(&[("TERM", "xterm")]), true),
+        ColorDepth::Ansi16
+    );
+    assert_eq!(
+        ColorDepth::from_env(env(&[("TERM", "dumb")]), true),
+        ColorDepth::None
+    );
+    let forced = &[("CLICOLOR_FORCE", "1"), ("TERM", "xterm-256color")];
+    assert_eq!(
+        ColorDepth::from_env(env(forced), false),
+        ColorDepth::Ansi256
+    );
+    let no_color = &[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")];
+    assert_eq!(ColorDepth::from_env(env(no_color), true), ColorDepth::None);
+}
+
+#[test]
+fn color_depth_convert() {
+    let text = format!("{}label{RESET}", AnsiStyle::new_rgb(197, 15, 12));
+    assert_eq!(ColorDepth::TrueColor.convert(&text), text);
+    assert_eq!(
+        ColorDepth::Ansi256.convert(&text),
+        "\u{1b}[38;5;160mlabel\u{1b}[0m"
+    );
+    assert_eq!(
+        ColorDepth::Ansi16.convert(&text),
+        "\u{1b}[31mlabel\u{1b}[0m"
+    );
+    assert_eq!(ColorDepth::None.convert(&text), "label");
+    // Malformed colors are left untouched, including their parameters
+    let malformed = "\u{1b}[1;38;5;300;4mlabel\u{1b}[38;2;1;2m";
+    assert_eq!(ColorDepth::Ansi16.convert(malformed), malformed);
+    // Grays map onto the grayscale ramp rather than the cube
+    assert_eq!(RgbColor::new(128, 128, 128).to_ansi256(), 244);
+    assert_eq!(RgbColor::from_ansi256(244), RgbColor::new(128, 128, 128));
+}
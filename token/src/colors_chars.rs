@@ -541,49 +541,79 @@ impl From<Resets> for AnsiStyle {
     }
 }
 
-pub fn colors() -> impl Iterator<Item = RgbColor> {
-    use super::saturating::SaturatingArithmetic;
-    // VIP SECTION: Color generation
-    // Just a placeholder for debugging purposes
-    fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
-        let c = (1.0.sat_sub(2.0.sat_mul(l).sat_sub(1.0)).abs()).sat_mul(s);
-        let x = c.sat_mul(
-            1.0.sat_sub((h.sat_div(60.0)).sat_rem(2.0).sat_sub(1.0))
-                .abs(),
-        );
-        let m = l.sat_sub(c.sat_div(2.0));
-
-        let (r1, g1, b1) = if (0.0..60.0).contains(&h) {
-            (c, x, 0.0)
-        } else if (60.0..120.0).contains(&h) {
-            (x, c, 0.0)
-        } else if (120.0..180.0).contains(&h) {
-            (0.0, c, x)
-        } else if (180.0..240.0).contains(&h) {
-            (0.0, x, c)
-        } else if (240.0..300.0).contains(&h) {
-            (x, 0.0, c)
-        } else {
-            (c, 0.0, x)
-        };
-
-        (
-            ((r1.sat_add(m)).sat_mul(255.0)).round() as u8,
-            ((g1.sat_add(m)).sat_mul(255.0)).round() as u8,
-            ((b1.sat_add(m)).sat_mul(255.0)).round() as u8,
-        )
-    }
-
-    let rand_start = (std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos() as u64)
-        .sat_rem(360);
-
-    // Generate distinct colors for each label if not set
-    (rand_start..).map(|i| {
+/// Deterministic generator of distinct label colors
+///
+/// The same seed always yields the same colors in the same order,
+/// so a report looks the same on every run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Palette {
+    seed: u64,
+}
+impl Palette {
+    pub const fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// The color of the label with the given index
+    pub fn color(&self, index: usize) -> RgbColor {
+        use super::saturating::SaturatingArithmetic;
+        // Wrapping, as the hue only depends on the remainder anyway
+        let i = self.seed.wrapping_add(index as u64).sat_rem(360);
         let hue = ((i as f32).sat_mul(137.508)).sat_rem(360.0); // use golden angle approximation
         let (r, g, b) = hsl_to_rgb(hue, 0.5, 0.5);
         RgbColor::new(r, g, b)
-    })
+    }
+    /// All colors of the palette, starting with the one of the first label
+    pub fn iter(&self) -> impl Iterator<Item = RgbColor> + use<> {
+        let palette = *self;
+        (0..).map(move |index| palette.color(index))
+    }
+}
+
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
+    use super::saturating::SaturatingArithmetic;
+    let c = (1.0.sat_sub(2.0.sat_mul(l).sat_sub(1.0)).abs()).sat_mul(s);
+    let x = c.sat_mul(
+        1.0.sat_sub((h.sat_div(60.0)).sat_rem(2.0).sat_sub(1.0))
+            .abs(),
+    );
+    let m = l.sat_sub(c.sat_div(2.0));
+
+    let (r1, g1, b1) = if (0.0..60.0).contains(&h) {
+        (c, x, 0.0)
+    } else if (60.0..120.0).contains(&h) {
+        (x, c, 0.0)
+    } else if (120.0..180.0).contains(&h) {
+        (0.0, c, x)
+    } else if (180.0..240.0).contains(&h) {
+        (0.0, x, c)
+    } else if (240.0..300.0).contains(&h) {
+        (x, 0.0, c)
+    } else {
+        (c, 0.0, x)
+    };
+
+    (
+        ((r1.sat_add(m)).sat_mul(255.0)).round() as u8,
+        ((g1.sat_add(m)).sat_mul(255.0)).round() as u8,
+        ((b1.sat_add(m)).sat_mul(255.0)).round() as u8,
+    )
+}
+
+/// Distinct colors for labels, the same on every run
+pub fn colors() -> impl Iterator<Item = RgbColor> {
+    Palette::default().iter()
+}
+
+#[test]
+fn palette_is_deterministic() {
+    let first = Palette::new(42).iter().take(8).collect::<Vec<_>>();
+    let second = Palette::new(42).iter().take(8).collect::<Vec<_>>();
+    assert_eq!(first, second);
+    assert_eq!(Palette::new(42).color(3), first[3]);
+    // Neighbouring labels never share a color
+    assert!(first.windows(2).all(|pair| pair[0] != pair[1]));
+    assert_ne!(Palette::new(7).color(0), first[0]);
 }
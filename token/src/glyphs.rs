@@ -0,0 +1,76 @@
+use super::*;
+
+/// The characters the carets, arrows and connectors of a report are drawn with
+///
+/// Every glyph must take up a single terminal cell, as the layout does not account for wider ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlyphSet {
+    /// Vertical caret (│)
+    pub v_caret: &'static str,
+    /// Horizontal caret (─)
+    pub h_caret: &'static str,
+    /// Down caret (┬)
+    pub h_down: &'static str,
+    /// Down-right caret (╭)
+    pub down_right: &'static str,
+    /// Up-right caret (╰)
+    pub up_right: &'static str,
+    /// Vertical-right caret (├)
+    pub v_right: &'static str,
+    /// Vertical-left caret (┤)
+    pub v_left: &'static str,
+    /// Left Arrow (▶)
+    pub l_arrow: &'static str,
+    /// Right Arrow (◀)
+    pub r_arrow: &'static str,
+}
+
+impl GlyphSet {
+    /// Box-drawing characters with rounded corners, the default
+    pub const UNICODE_ROUNDED: Self = Self {
+        v_caret: V_CARET,
+        h_caret: H_CARET,
+        h_down: H_DOWN,
+        down_right: DOWN_RIGHT,
+        up_right: UP_RIGHT,
+        v_right: V_RIGHT,
+        v_left: V_LEFT,
+        l_arrow: L_ARROW,
+        r_arrow: R_ARROW,
+    };
+    /// Box-drawing characters with square corners
+    pub const UNICODE_SQUARE: Self = Self {
+        down_right: "┌",
+        up_right: "└",
+        ..Self::UNICODE_ROUNDED
+    };
+    /// Heavy box-drawing characters
+    pub const HEAVY: Self = Self {
+        v_caret: "┃",
+        h_caret: "━",
+        h_down: "┳",
+        down_right: "┏",
+        up_right: "┗",
+        v_right: "┣",
+        v_left: "┫",
+        ..Self::UNICODE_ROUNDED
+    };
+    /// Plain ASCII, for terminals and log viewers that mangle anything else
+    pub const ASCII: Self = Self {
+        v_caret: "|",
+        h_caret: "-",
+        h_down: "+",
+        down_right: ",",
+        up_right: "`",
+        v_right: "|",
+        v_left: "|",
+        l_arrow: ">",
+        r_arrow: "<",
+    };
+}
+
+impl Default for GlyphSet {
+    fn default() -> Self {
+        Self::UNICODE_ROUNDED
+    }
+}
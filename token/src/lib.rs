@@ -10,13 +10,18 @@ use ::std::{
 
 mod colors_chars;
 pub use colors_chars::*;
+mod color_depth;
+pub use color_depth::*;
 mod token;
 pub use token::*;
 mod token_stream;
 pub use token_stream::*;
 mod multiline_stream;
 pub use multiline_stream::*;
+mod glyphs;
+pub use glyphs::*;
 pub mod saturating;
+pub mod width;
 
 #[macro_export(local_inner_macros)]
 macro_rules! impl_field {
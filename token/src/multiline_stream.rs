@@ -228,9 +228,13 @@ impl LineTokenStream {
                 // Need to break the line
                 // We wanna try to break it at whitespace but at max "max_line_length" if possible, otherwise break it with a offset of 1 and add a hyphen
                 while line.len() > max_line_length {
-                    let break_at = line[..max_line_length]
+                    // Never split a character, but always make progress
+                    let fallback = line
+                        .floor_char_boundary(max_line_length.sat_sub(1))
+                        .max(line.ceil_char_boundary(1));
+                    let break_at = line[..line.floor_char_boundary(max_line_length)]
                         .rfind(char::is_whitespace)
-                        .unwrap_or(max_line_length.sat_sub(1));
+                        .unwrap_or(fallback);
                     // Check if we can break within range
                     if break_at > max_line_length {
                         // We need to break with a hyphen
@@ -284,8 +288,13 @@ impl LineTokenStream {
                         line = rem.trim_start().to_string();
                     }
                 }
-            } else {
-                // Just add the line as is
+            }
+            // Add the (remaining part of the) line as is
+            //
+            // This used to be skipped for every line that had to be wrapped, silently dropping
+            // the text after its last break (e.g. "label" of "This is another child label" wrapped at 22).
+            // It affected every wrapped label, the footers only made it obvious.
+            if !line.is_empty() {
                 if let Ok(line_stream) = TokenStream::from_str(&line) {
                     current_line.extend(line_stream);
                 } else {
@@ -500,3 +509,13 @@ impl AsMut<LineTokenStream> for TokenStreamLineIntoIter {
         &mut self.stream
     }
 }
+
+#[test]
+fn from_str_with_length_keeps_remainder() {
+    let stream = LineTokenStream::from_str_with_length("This is another child label", 22);
+    let lines = stream
+        .lines()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(lines, ["This is another child", "label"]);
+}
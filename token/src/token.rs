@@ -11,6 +11,8 @@ pub enum Token {
     HCaret(usize),
     /// Down caret (┬)
     HDown,
+    /// Down-right caret (╭)
+    DownRight,
     /// Up-right caret (╰)
     UpRight,
     /// Vertical-right caret (├)
@@ -164,6 +166,43 @@ impl Token {
         H_CARET.repeat(amount)
     }
 
+    /// The text of a caret or arrow token in the given glyph set, None for every other token
+    pub fn glyph(&self, glyphs: &GlyphSet) -> Option<String> {
+        Some(match self {
+            Token::VCaret => glyphs.v_caret.to_string(),
+            Token::HCaret(amount) => glyphs.h_caret.repeat(*amount),
+            Token::HDown => glyphs.h_down.to_string(),
+            Token::DownRight => glyphs.down_right.to_string(),
+            Token::UpRight => glyphs.up_right.to_string(),
+            Token::VRight => glyphs.v_right.to_string(),
+            Token::VLeft => glyphs.v_left.to_string(),
+            Token::LArrow => glyphs.l_arrow.to_string(),
+            Token::RArrow => glyphs.r_arrow.to_string(),
+            _ => return None,
+        })
+    }
+
+    /// The token without any styling, None if there is nothing left but styling
+    pub fn strip_styles(self) -> Option<Token> {
+        match self {
+            Token::Styled(_, inner) => inner.and_then(|inner| inner.strip_styles()),
+            Token::Reset => None,
+            token => Some(token),
+        }
+    }
+
+    /// Replaces caret and arrow tokens (including styled ones) by literals drawn with the given glyph set
+    pub fn apply_glyphs(&mut self, glyphs: &GlyphSet) {
+        match self {
+            Token::Styled(_, Some(inner)) => inner.apply_glyphs(glyphs),
+            token => {
+                if let Some(glyph) = token.glyph(glyphs) {
+                    *token = Token::Literal(glyph);
+                }
+            }
+        }
+    }
+
     pub fn len(&self) -> usize {
         match self {
             Token::HCaret(amount) => *amount,
@@ -174,6 +213,15 @@ impl Token {
         }
     }
 
+    /// Just like `len`, but counts literals by the terminal cells they take rather than their bytes
+    pub fn width(&self) -> usize {
+        match self {
+            Token::Literal(label) => width::display_width(label),
+            Token::Styled(_, inner) => inner.as_ref().map_or(1, |b| b.width().sat_add(1)),
+            token => token.len(),
+        }
+    }
+
     pub fn is_mergeable(&self) -> bool {
         #[cfg(feature = "merging_tokens")]
         {
@@ -420,6 +468,7 @@ impl Token {
             '│' => Token::VCaret,
             '─' => Token::HCaret(chars.take_while_ref(|&c| c == '─').count().sat_add(1)),
             '┬' => Token::HDown,
+            '╭' => Token::DownRight,
             '╰' => Token::UpRight,
             '├' => Token::VRight,
             '┤' => Token::VLeft,
@@ -502,14 +551,21 @@ impl Token {
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Token::VCaret => write!(f, "{}", Self::V_CARET),
-            Token::HCaret(amount) => write!(f, "{}", Self::H_CARET(*amount)),
-            Token::HDown => write!(f, "{}", Self::H_DOWN),
-            Token::UpRight => write!(f, "{}", Self::UP_RIGHT),
-            Token::VRight => write!(f, "{}", Self::V_RIGHT),
-            Token::VLeft => write!(f, "{}", Self::V_LEFT),
-            Token::LArrow => write!(f, "{}", Self::L_ARROW),
-            Token::RArrow => write!(f, "{}", Self::R_ARROW),
+            Token::VCaret
+            | Token::HCaret(_)
+            | Token::HDown
+            | Token::DownRight
+            | Token::UpRight
+            | Token::VRight
+            | Token::VLeft
+            | Token::LArrow
+            | Token::RArrow => {
+                write!(
+                    f,
+                    "{}",
+                    self.glyph(&GlyphSet::default()).unwrap_or_default()
+                )
+            }
             Token::Space(amount) => write!(f, "{}", Self::SPACE(*amount)),
             Token::Literal(label) => write!(f, "{}", label),
             Token::Styled(style, token) => {
@@ -541,3 +597,11 @@ impl IntoIterator for Token {
         std::iter::once(self)
     }
 }
+
+#[test]
+fn len_counts_bytes_width_counts_cells() {
+    let crab = Token::Literal("🦀 crab".into());
+    assert_eq!(crab.len(), 9);
+    assert_eq!(crab.width(), 7);
+    assert_eq!(Token::Space(3).width(), 3);
+}
@@ -69,6 +69,11 @@ impl TokenStream {
         self.tokens.iter().map(|tkn| tkn.len()).sum()
     }
 
+    /// The number of terminal cells the stream takes, see `Token::width`
+    pub fn width(&self) -> usize {
+        self.tokens.iter().map(|tkn| tkn.width()).sum()
+    }
+
     pub fn on_color<I: Into<AnsiStyle>>(&mut self, style: I) {
         if let Some(first) = self.tokens.first_mut() {
             *first = Token::Styled(style.into(), Some(Box::new(first.clone())));
@@ -192,6 +197,21 @@ impl TokenStream {
         }
     }
 
+    /// Drops all styled and reset tokens, keeping the text of styled ones
+    pub fn strip_styles(&mut self) {
+        self.tokens = std::mem::take(&mut self.tokens)
+            .into_iter()
+            .filter_map(Token::strip_styles)
+            .collect();
+    }
+
+    /// Draws every caret and arrow of the stream with the given glyph set
+    pub fn apply_glyphs(&mut self, glyphs: &GlyphSet) {
+        self.tokens
+            .iter_mut()
+            .for_each(|token| token.apply_glyphs(glyphs));
+    }
+
     pub fn insert<T: Into<Token>>(&mut self, index: usize, item: T) {
         let mut item = item.into();
         if index >= self.tokens.len() {
@@ -0,0 +1,87 @@
+//! Mapping between byte offsets and terminal columns
+//!
+//! Columns are counted in terminal cells: East Asian wide characters (and most emoji) take two cells,
+//! combining characters take none and are attributed to the grapheme they belong to.
+
+use ::unicode_segmentation::UnicodeSegmentation;
+use ::unicode_width::UnicodeWidthStr;
+
+use crate::saturating::SaturatingArithmetic;
+
+/// The number of terminal cells the given text occupies
+pub fn display_width(s: &str) -> usize {
+    s.graphemes(true).map(UnicodeWidthStr::width).sum()
+}
+
+/// The (0-based) columns occupied by the grapheme containing the given byte offset
+///
+/// Offsets past the end of the text are treated as if the text was padded with single-cell characters,
+/// so that e.g. a label pointing at the line terminator still gets a column.
+/// The returned range is never empty, even for zero-width graphemes.
+pub fn columns_of(s: &str, offset: usize) -> std::ops::Range<usize> {
+    let mut column = 0;
+    for (start, grapheme) in s.grapheme_indices(true) {
+        let width = grapheme.width();
+        if offset < start.sat_add(grapheme.len()) {
+            return column..column.sat_add(width.max(1));
+        }
+        column = column.sat_add(width);
+    }
+    let column = column.sat_add(offset.sat_sub(s.len()));
+    column..column.sat_add(1)
+}
+
+/// The byte offset of the first grapheme starting at or after the given (0-based) column
+///
+/// Returns the length of the text if the column is past its end.
+/// As graphemes are never split, the returned offset is always on a character boundary.
+pub fn offset_of(s: &str, column: usize) -> usize {
+    let mut current = 0;
+    for (start, grapheme) in s.grapheme_indices(true) {
+        if current >= column {
+            return start;
+        }
+        current = current.sat_add(grapheme.width());
+    }
+    s.len()
+}
+
+#[test]
+fn width_of_non_ascii() {
+    assert_eq!(display_width("abc"), 3);
+    assert_eq!(display_width("日本語"), 6);
+    // "e" followed by a combining acute accent
+    assert_eq!(display_width("e\u{301}"), 1);
+    assert_eq!(display_width("🦀"), 2);
+}
+
+#[test]
+fn columns_of_non_ascii() {
+    let s = "a日e\u{301}🦀b";
+    assert_eq!(columns_of(s, 0), 0..1);
+    // Every byte of the wide character maps onto both of its cells
+    assert_eq!(columns_of(s, 1), 1..3);
+    assert_eq!(columns_of(s, 3), 1..3);
+    // The combining accent belongs to the "e"
+    assert_eq!(columns_of(s, 4), 3..4);
+    assert_eq!(columns_of(s, 5), 3..4);
+    assert_eq!(columns_of(s, 7), 4..6);
+    assert_eq!(columns_of(s, 11), 6..7);
+    // Past the end
+    assert_eq!(columns_of(s, 12), 7..8);
+    assert_eq!(columns_of(s, 13), 8..9);
+}
+
+#[test]
+fn offset_of_non_ascii() {
+    let s = "a日e\u{301}🦀b";
+    assert_eq!(offset_of(s, 0), 0);
+    assert_eq!(offset_of(s, 1), 1);
+    // Within the wide character, so the next grapheme is used
+    assert_eq!(offset_of(s, 2), 4);
+    assert_eq!(offset_of(s, 3), 4);
+    assert_eq!(offset_of(s, 4), 7);
+    assert_eq!(offset_of(s, 6), 11);
+    assert_eq!(offset_of(s, 7), 12);
+    assert_eq!(offset_of(s, 100), 12);
+}